@@ -9,13 +9,13 @@ use crate::types::repository::get_current_repository;
 ///
 /// # Arguments
 ///
-/// * `files` - A reference to a vector of strings representing file paths to add.
+/// * `files` - A slice of strings representing file paths to add.
 /// * `repo` - An optional reference to a `Repository`. If `None`, the function tries
-///            to find the current repository automatically.
+///   to find the current repository automatically.
 ///
 /// # Errors
 ///
-/// Returns an `Err(String)` if:
+/// Returns an `Err(Error)` if:
 /// - The repository cannot be determined.
 /// - The repository index cannot be accessed.
 /// - Adding any of the specified files to the index fails.
@@ -23,22 +23,19 @@ use crate::types::repository::get_current_repository;
 ///
 /// # Returns
 ///
-/// * `Ok(0)` on success.
-pub fn add(files: &Vec<String>, repo: Option<&Repository>) -> Result<(), Error> {
+/// * `Ok(())` on success.
+pub fn add(files: &[String], repo: Option<&Repository>) -> Result<(), Error> {
     let owned_repo;
     let current_repo = match repo{
         Some(r) => r,
         _ => {
-            owned_repo = get_current_repository().map_err(|e| e)?;
+            owned_repo = get_current_repository()?;
             &owned_repo
         }
     };
 
     // get the index (staging area)
-    let mut index = match current_repo.index() {
-        Ok(index) => index,
-        Err(e) => return Err(e),
-    };
+    let mut index = current_repo.index()?;
 
     let corrected_files: Vec<String> = if files[0] == "." {
             vec![files[0].to_string()]
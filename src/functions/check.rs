@@ -0,0 +1,309 @@
+use git2::{Error, ErrorClass, ErrorCode, Oid, Repository, RevparseMode, Sort};
+use serde_json::json;
+use crate::functions::lint::{Diagnostic, Linter, Severity};
+use crate::types::commands::ReportFormat;
+use crate::types::repository::get_current_repository;
+
+/// The lint result of one commit of a checked range.
+///
+/// # Fields
+///
+/// * `id` - The commit id.
+/// * `summary` - The first line of the commit message.
+/// * `skipped` - Whether the commit was not linted (merge commits, when
+///   `skip_merges` is set).
+/// * `diagnostics` - The findings of the linter.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommitReport {
+    pub id: Oid,
+    pub summary: String,
+    pub skipped: bool,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl CommitReport {
+    /// Returns the number of findings of the given severity.
+    pub fn count(&self, severity: Severity) -> usize {
+        self.diagnostics.iter().filter(|d| d.severity == severity).count()
+    }
+
+    fn short_id(&self) -> String {
+        self.id.to_string()[..7].to_string()
+    }
+}
+
+/// Lints every commit of a revision range, oldest first.
+///
+/// The range is anything `git rev-parse` accepts: `origin/main..HEAD`,
+/// `A...B` (the commits reachable from either side but not both) or a single
+/// revision (its whole history).
+///
+/// # Errors
+///
+/// Returns an `Err(Error)` if the range cannot be resolved or walked.
+pub fn check_range(repo: &Repository, linter: &Linter, range: &str) -> Result<Vec<CommitReport>, Error> {
+    let spec = repo.revparse(range)?;
+    let mut revwalk = repo.revwalk()?;
+    revwalk.set_sorting(Sort::TOPOLOGICAL | Sort::REVERSE)?;
+
+    if spec.mode().contains(RevparseMode::SINGLE) {
+        if let Some(from) = spec.from() {
+            revwalk.push(from.peel_to_commit()?.id())?;
+        }
+    } else {
+        let (from, to) = match (spec.from(), spec.to()) {
+            (Some(from), Some(to)) => (from.peel_to_commit()?.id(), to.peel_to_commit()?.id()),
+            _ => return Err(Error::from_str(format!("invalid revision range '{}'", range).as_str())),
+        };
+        revwalk.push(to)?;
+        if spec.mode().contains(RevparseMode::MERGE_BASE) {
+            revwalk.push(from)?;
+            if let Ok(base) = repo.merge_base(from, to) {
+                revwalk.hide(base)?;
+            }
+        } else {
+            revwalk.hide(from)?;
+        }
+    }
+
+    let skip_merges = linter.config().skip_merges;
+    let mut reports = Vec::new();
+    for oid in revwalk {
+        let commit = repo.find_commit(oid?)?;
+        let message = String::from_utf8_lossy(commit.message_bytes()).to_string();
+        let skipped = skip_merges && commit.parent_count() > 1;
+        reports.push(CommitReport {
+            id: commit.id(),
+            summary: message.lines().next().unwrap_or_default().to_string(),
+            skipped,
+            diagnostics: if skipped { Vec::new() } else { linter.lint_committed(&message) },
+        });
+    }
+    Ok(reports)
+}
+
+// escapes text for XML attributes and content
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// Renders the reports of a checked range in the requested format.
+///
+/// * `Text` - One line per commit, followed by its findings, and a summary.
+/// * `Json` - An object with the `commits` and the `errors`/`warnings` totals.
+/// * `Junit` - A JUnit XML test suite with one test case per commit; commits
+///   with errors are failures and skipped commits are skipped tests.
+pub fn render_report(reports: &[CommitReport], format: ReportFormat) -> String {
+    let errors: usize = reports.iter().map(|r| r.count(Severity::Error)).sum();
+    let warnings: usize = reports.iter().map(|r| r.count(Severity::Warning)).sum();
+
+    match format {
+        ReportFormat::Text => {
+            let mut output = String::new();
+            for report in reports {
+                let status = if report.skipped {
+                    "skip"
+                } else if report.count(Severity::Error) > 0 {
+                    "FAIL"
+                } else {
+                    "ok"
+                };
+                output.push_str(&format!("{:<4} {} {}\n", status, report.short_id(), report.summary));
+                for diagnostic in &report.diagnostics {
+                    output.push_str(&format!("     {}\n", diagnostic));
+                }
+            }
+            let failed = reports.iter().filter(|r| r.count(Severity::Error) > 0).count();
+            output.push_str(&format!(
+                "{} commit(s) checked, {} failed: {} error(s), {} warning(s)\n",
+                reports.len(), failed, errors, warnings
+            ));
+            output
+        }
+        ReportFormat::Json => {
+            let commits: Vec<serde_json::Value> = reports.iter()
+                .map(|report| json!({
+                    "id": report.id.to_string(),
+                    "summary": report.summary,
+                    "skipped": report.skipped,
+                    "diagnostics": report.diagnostics.iter()
+                        .map(|d| json!({
+                            "rule": d.rule,
+                            "line": d.line,
+                            "column": d.column,
+                            "severity": d.severity.to_string(),
+                            "message": d.message,
+                        }))
+                        .collect::<Vec<serde_json::Value>>(),
+                }))
+                .collect();
+            let report = json!({ "commits": commits, "errors": errors, "warnings": warnings });
+            format!("{}\n", serde_json::to_string_pretty(&report).unwrap_or_default())
+        }
+        ReportFormat::Junit => {
+            let failures = reports.iter().filter(|r| r.count(Severity::Error) > 0).count();
+            let skipped = reports.iter().filter(|r| r.skipped).count();
+            let mut output = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+            output.push_str(&format!(
+                "<testsuites>\n  <testsuite name=\"glyphit check\" tests=\"{}\" failures=\"{}\" skipped=\"{}\">\n",
+                reports.len(), failures, skipped
+            ));
+            for report in reports {
+                output.push_str(&format!(
+                    "    <testcase classname=\"glyphit.check\" name=\"{} {}\">\n",
+                    report.short_id(), xml_escape(&report.summary)
+                ));
+                let details: Vec<String> = report.diagnostics.iter().map(|d| xml_escape(&d.to_string())).collect();
+                if report.skipped {
+                    output.push_str("      <skipped message=\"merge commit\"/>\n");
+                } else if report.count(Severity::Error) > 0 {
+                    output.push_str(&format!(
+                        "      <failure message=\"{} error(s)\">{}</failure>\n",
+                        report.count(Severity::Error), details.join("\n")
+                    ));
+                } else if !details.is_empty() {
+                    output.push_str(&format!("      <system-out>{}</system-out>\n", details.join("\n")));
+                }
+                output.push_str("    </testcase>\n");
+            }
+            output.push_str("  </testsuite>\n</testsuites>\n");
+            output
+        }
+    }
+}
+
+/// Lints every commit message of a revision range and prints a report, for
+/// instance to gate pull requests in CI.
+///
+/// # Arguments
+///
+/// * `repo` - An optional reference to a `Repository`. If `None`, the function
+///   attempts to find the current repository automatically.
+/// * `range` - The revision range, e.g. `origin/main..HEAD`.
+/// * `format` - The report format: text, JSON or JUnit XML.
+///
+/// # Errors
+///
+/// Returns an `Err(Error)` if:
+/// - The current repository cannot be determined.
+/// - The configuration, emoji catalog or template cannot be loaded.
+/// - The range cannot be resolved or walked.
+/// - At least one commit has an error-level finding.
+///
+/// # Returns
+///
+/// * `Ok(())` if no commit of the range has errors.
+pub fn check(repo: Option<&Repository>, range: &str, format: ReportFormat) -> Result<(), Error> {
+    let owned_repo;
+    let current_repo = match repo {
+        Some(r) => r,
+        _ => {
+            owned_repo = get_current_repository()?;
+            &owned_repo
+        }
+    };
+    let linter = Linter::load(current_repo)?;
+
+    let reports = check_range(current_repo, &linter, range)?;
+    print!("{}", render_report(&reports, format));
+
+    let failed = reports.iter().filter(|r| r.count(Severity::Error) > 0).count();
+    if failed > 0 {
+        return Err(Error::new(ErrorCode::Invalid, ErrorClass::Invalid, format!(
+            "{} of {} commit(s) in '{}' do not follow the commit rules", failed, reports.len(), range
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use git2::{Commit, Signature};
+    use tempfile::tempdir;
+
+    fn commit_on<'r>(repo: &'r Repository, message: &str, parents: &[&Commit]) -> Commit<'r> {
+        let signature = Signature::now("Test User", "test@example.com").unwrap();
+        let tree = repo.find_tree(repo.index().unwrap().write_tree().unwrap()).unwrap();
+        let oid = repo.commit(None, &signature, &signature, message, &tree, parents).unwrap();
+        repo.find_commit(oid).unwrap()
+    }
+
+    // main: base; feature: base - good - bad - merge(main)
+    fn history(repo: &Repository) -> (Commit<'_>, Commit<'_>) {
+        let base = commit_on(repo, "🎉 begin\n", &[]);
+        let other = commit_on(repo, "📝 document\n", &[&base]);
+        let good = commit_on(repo, "✨ add login form\n", &[&base]);
+        let bad = commit_on(repo, "fix crash.\n", &[&good]);
+        let merge = commit_on(repo, "Merge branch 'main'\n", &[&bad, &other]);
+        (base, merge)
+    }
+
+    #[test]
+    fn test_check_range() {
+        let temp_dir = tempdir().unwrap();
+        let repo = Repository::init(temp_dir.path()).unwrap();
+        let (base, merge) = history(&repo);
+        let linter = Linter::load(&repo).unwrap();
+
+        let range = format!("{}..{}", base.id(), merge.id());
+        let reports = check_range(&repo, &linter, &range).unwrap();
+        let summaries: Vec<&str> = reports.iter().map(|r| r.summary.as_str()).collect();
+        assert_eq!(summaries.len(), 4);
+        assert_eq!(summaries[3], "Merge branch 'main'");
+        assert!(summaries[..3].contains(&"fix crash."));
+
+        let failed: Vec<&str> = reports.iter()
+            .filter(|r| r.count(Severity::Error) > 0)
+            .map(|r| r.summary.as_str())
+            .collect();
+        assert_eq!(failed, vec!["fix crash."]);
+        assert!(reports[3].skipped);
+
+        repo.config().unwrap().set_bool("glyphit.skipMerges", false).unwrap();
+        let linter = Linter::load(&repo).unwrap();
+        let reports = check_range(&repo, &linter, &range).unwrap();
+        assert!(!reports[3].skipped);
+        assert_eq!(reports[3].count(Severity::Error), 1);
+
+        assert_eq!(check_range(&repo, &linter, &merge.id().to_string()).unwrap().len(), 5);
+        assert!(check_range(&repo, &linter, "nope..HEAD").is_err());
+    }
+
+    #[test]
+    fn test_render_report() {
+        let temp_dir = tempdir().unwrap();
+        let repo = Repository::init(temp_dir.path()).unwrap();
+        let (base, merge) = history(&repo);
+        let linter = Linter::load(&repo).unwrap();
+        let reports = check_range(&repo, &linter, &format!("{}..{}", base.id(), merge.id())).unwrap();
+
+        let text = render_report(&reports, ReportFormat::Text);
+        assert!(text.contains("error[leading-emoji]"));
+        assert!(text.ends_with("4 commit(s) checked, 1 failed: 1 error(s), 1 warning(s)\n"));
+
+        let json: serde_json::Value = serde_json::from_str(&render_report(&reports, ReportFormat::Json)).unwrap();
+        assert_eq!(json["errors"], 1);
+        assert_eq!(json["commits"].as_array().unwrap().len(), 4);
+        assert_eq!(json["commits"][3]["skipped"], true);
+
+        let junit = render_report(&reports, ReportFormat::Junit);
+        assert!(junit.contains("tests=\"4\" failures=\"1\" skipped=\"1\""));
+        assert!(junit.contains("Merge branch &apos;main&apos;"));
+        assert_eq!(junit.matches("<failure").count(), 1);
+    }
+
+    #[test]
+    fn test_check_fails_on_errors() {
+        let temp_dir = tempdir().unwrap();
+        let repo = Repository::init(temp_dir.path()).unwrap();
+        let (base, merge) = history(&repo);
+
+        assert!(check(Some(&repo), &format!("{}..{}", base.id(), merge.id()), ReportFormat::Text).is_err());
+        assert!(check(Some(&repo), &base.id().to_string(), ReportFormat::Json).is_ok());
+    }
+}
@@ -1,7 +1,24 @@
 use std::io::Write;
-use git2::{Error, ErrorClass, ErrorCode, Oid, Repository, Signature};
-use inquire::{InquireError, Select};
-use crate::types::repository::get_current_repository;
+use git2::{Commit, Delta, Error, ErrorClass, ErrorCode, Oid, Repository, Signature};
+use inquire::{InquireError, Select, Text};
+use crate::types::commands::CommitArgs;
+use crate::functions::editor::{clean_message, edit_file, edit_template};
+use crate::functions::history::{frequent_emojis, record_usage};
+use crate::functions::hooks::{run_hook, run_hook_with_input};
+use crate::functions::identity::{format_date, resolve_identities};
+use crate::functions::issue::{branch_matches, current_branch, issue_from_branch, mentions_issue};
+use crate::functions::sign::Signer;
+use crate::functions::scope::{scope_options, suggest_scopes, workspace_members};
+use crate::functions::search::{fuzzy_score, search};
+use crate::functions::suggest::{rank_first, suggest_for_repository};
+use crate::functions::trailers::{
+    issue_reference, known_authors, resolve_author, AuthorCompleter, CLOSES, CO_AUTHORED_BY, REFS, SIGNED_OFF_BY,
+};
+use crate::types::config::{Config, IssuePlacement};
+use crate::types::emoji::{Emoji, EmojiCatalog};
+use crate::types::template::{strip_autosquash, MessageParts, Template, BREAKING_CHANGE};
+use crate::types::repository::{comment_char, get_current_repository};
+use crate::types::staged::{staged_changes, staged_stats, unstaged_changes, FileStat};
 
 fn user_input(message: String) -> String {
     use std::io;
@@ -17,201 +34,690 @@ fn user_input(message: String) -> String {
     input.trim().to_string()
 }
 
-fn select_emoji() -> Result<String, InquireError> {
-    let options = vec![
-        "🎨 :art: Improve structure/format",
-        "⚡ :zap: Improve performance",
-        "🔥 :fire: Remove code or files",
-        "🐛 :bug: Fix a bug",
-        "🚑️ :ambulance: Critical hotfix",
-        "✨ :sparkles: Introduce new features",
-        "📝 :memo: Add or update documentation",
-        "🚀 :rocket: Deploy or release something",
-        "💄 :lipstick: Add or update UI/style files",
-        "🎉 :tada: Initial commit",
-        "✅ :white_check_mark: Add, update, or pass tests",
-        "🔒 :lock: Fix or improve security issues",
-        "🔐 :closed_lock_with_key: Add or update secrets",
-        "🔖 :bookmark: Release or version tags",
-        "🚨 :rotating_light: Fix compiler/linter warnings",
-        "🚧 :construction: Work in progress",
-        "💚 :green_heart: Fix CI build",
-        "⬇️ :arrow_down: Downgrade dependencies",
-        "⬆️ :arrow_up: Upgrade dependencies",
-        "📌 :pushpin: Pin dependencies to specific versions",
-        "👷 :construction_worker: Add or update CI/CD build system",
-        "📈 :chart_with_upwards_trend: Add or update analytics/tracking code",
-        "♻️ :recycle: Refactor code",
-        "➕ :heavy_plus_sign: Add a dependency",
-        "➖ :heavy_minus_sign: Remove a dependency",
-        "🔧 :wrench: Add or update configuration files",
-        "🔨 :hammer: Add or update build scripts",
-        "🌐 :globe_with_meridians: Internationalization or localization",
-        "✏️ :pencil2: Fix typos",
-        "💩 :poop: Write bad code that needs improvement",
-        "⏪ :rewind: Revert changes",
-        "🔀 :twisted_rightwards_arrows: Merge branches",
-        "📦 :package: Add or update compiled files or dependencies",
-        "👽 :alien: Update code due to external API changes",
-        "🚚 :truck: Move or rename files",
-        "📄 :page_facing_up: Add or update license",
-        "💥 :boom: Introduce breaking changes",
-        "🍱 :bento: Add or update assets",
-        "♿ :wheelchair: Improve accessibility",
-        "💡 :bulb: Add or update comments in source code",
-        "🍻 :beers: Celebrate or add fun Easter eggs",
-        "💬 :speech_balloon: Add or update text and messages",
-        "🗃️ :card_file_box: Perform database-related changes",
-        "🔊 :loud_sound: Add or update logs",
-        "🔇 :mute: Remove logs",
-        "👥 :busts_in_silhouette: Add or update contributor(s)",
-        "🚸 :children_crossing: Improve UX or UI accessibility",
-        "🏗️ :building_construction: Make architectural changes",
-        "📱 :iphone: Work on responsive design or mobile support",
-        "🤡 :clown_face: Mock related changes",
-        "🥚 :egg: Add or update Easter eggs",
-        "🙈 :see_no_evil: Add or update .gitignore file",
-        "🧠 :brain: Add or update logic or algorithms",
-        "🧰 :toolbox: Add or update tooling/utilities",
-        "🧪 :test_tube: Add or update tests",
-        "🧱 :bricks: Infrastructure changes",
-        "🩹 :adhesive_bandage: Simple fix not critical",
-        "🩺 :stethoscope: Add or update health checks",
-        "🧩 :jigsaw: Add or update modular code/components",
-        "🧹 :broom: Remove useless files or code",
-        "🧵 :thread: Add or update multithreading/concurrency",
-        "🕹️ :joystick: Add or update scripts/tooling",
-        "🧑‍💻 :technologist: Improve developer experience",
-        "🗑️ :wastebasket: Deprecate or remove obsolete code",
-        "🏁 :checkered_flag: Finish a feature or milestone",
-        "🪄 :magic_wand: Minor visual or UX enhancements",
-    ];
-
-    let prompt = Select::new("Select an emoji for your commit:", options);
-
-    match prompt.prompt() {
-        Ok(choice) => Ok(choice.to_string()),
-        Err(e) => Err(e),
+// suggested then frequently used emojis are listed first, the cursor starts on
+// the most likely one; typing filters the list with the fuzzy matcher
+fn select_emoji(catalog: &EmojiCatalog, suggestions: &[String]) -> Result<Emoji, InquireError> {
+    let scorer = |input: &str, emoji: &Emoji, _: &str, _: usize| fuzzy_score(input, emoji);
+    let prompt = Select::new("Select an emoji for your commit:", rank_first(catalog.emojis(), suggestions))
+        .with_scorer(&scorer);
+
+    prompt.prompt()
+}
+
+/// Prompts for an emoji with the picker, listing first the `preferred`
+/// emojis (shortcodes), then the ones suggested by the staged changes, then
+/// the ones the user picks most often.
+///
+/// # Errors
+///
+/// Returns an `Err(Error)` if the staged changes or the usage history cannot
+/// be read, or the prompt fails or is cancelled.
+pub fn prompt_emoji(repo: &Repository, config: &Config, catalog: &EmojiCatalog, preferred: &[String]) -> Result<Emoji, Error> {
+    let mut suggestions = preferred.to_vec();
+    suggestions.extend(suggest_for_repository(repo, &config.suggestions)?);
+    suggestions.extend(frequent_emojis(repo, config.history, catalog)?);
+    select_emoji(catalog, &suggestions)
+        .map_err(|e| Error::new(ErrorCode::NotFound, ErrorClass::Invalid, e.to_string()))
+}
+
+// error message for an unknown `--emoji`, listing the closest matches
+fn unknown_emoji_message(catalog: &EmojiCatalog, query: &str) -> String {
+    let candidates: Vec<String> = search(catalog.emojis(), query).iter()
+        .take(5)
+        .map(|emoji| format!("{} :{}:", emoji.glyph, emoji.shortcode))
+        .collect();
+    if candidates.is_empty() {
+        format!("unknown emoji '{}'", query)
+    } else {
+        format!("unknown emoji '{}', did you mean: {}", query, candidates.join(", "))
+    }
+}
+
+const NO_SCOPE: &str = "(no scope)";
+const CUSTOM_SCOPE: &str = "(type a scope)";
+
+// offers the suggested scopes, with a free-text entry unless the scopes are restricted
+fn select_scope(options: Vec<String>, free_text: bool) -> Result<String, InquireError> {
+    if options.is_empty() && free_text {
+        return Ok(user_input("Provide a commit scope (optional) > ".to_string()));
+    }
+
+    let mut choices = options;
+    if free_text {
+        choices.push(CUSTOM_SCOPE.to_string());
+    }
+    choices.push(NO_SCOPE.to_string());
+
+    match Select::new("Select a scope for your commit:", choices).prompt()?.as_str() {
+        NO_SCOPE => Ok(String::new()),
+        CUSTOM_SCOPE => Ok(user_input("Provide a commit scope > ".to_string())),
+        scope => Ok(scope.to_string()),
+    }
+}
+
+// returns the supplied value, or prompts for it when interactive mode is
+// allowed; a non-empty `default` pre-fills the prompt, and is used as it is
+// when prompts are disabled
+fn resolve_input(value: &Option<String>, prompt: &str, interactive: bool, required: bool, default: &str) -> Result<String, Error> {
+    match value {
+        Some(v) => Ok(v.trim().to_string()),
+        None if interactive && !default.is_empty() => Text::new(prompt.trim_end_matches(" > "))
+            .with_initial_value(default)
+            .prompt()
+            .map(|input| input.trim().to_string())
+            .map_err(|e| Error::new(ErrorCode::NotFound, ErrorClass::Invalid, e.to_string())),
+        None if interactive => Ok(user_input(prompt.to_string())),
+        None if !default.is_empty() => Ok(default.to_string()),
+        None if required => Err(Error::from_str(
+            format!("missing required value for '{}' in non-interactive mode", prompt.trim_end_matches(" > ")).as_str()
+        )),
+        None => Ok(String::new()),
+    }
+}
+
+// asks for co-authors one at a time, completed from the known authors, until
+// an empty answer
+fn prompt_co_authors(known: &[String]) -> Result<Vec<String>, Error> {
+    let mut co_authors = Vec::new();
+    loop {
+        let input = Text::new("Add a co-author (optional):")
+            .with_autocomplete(AuthorCompleter::new(known.to_vec()))
+            .prompt()
+            .map_err(|e| Error::new(ErrorCode::NotFound, ErrorClass::Invalid, e.to_string()))?;
+        if input.trim().is_empty() {
+            return Ok(co_authors);
+        }
+        match resolve_author(&input, known) {
+            Ok(co_author) => co_authors.push(co_author),
+            Err(e) => eprintln!("{}", e.message()),
+        }
     }
 }
 
+// resolves the parts of the message, prompting for the missing ones, and
+// renders it with the template; `previous` holds the defaults when amending,
+// and `identity` (`Name <email>`) signs the commit off
+fn compose_message(
+    repo: &Repository,
+    config: &Config,
+    catalog: &EmojiCatalog,
+    args: &CommitArgs,
+    interactive: bool,
+    previous: &MessageParts,
+    identity: &str,
+) -> Result<(String, Emoji), Error> {
+    let template = Template::resolve(&config.template)?;
+    let previous_emoji = catalog.match_prefix(&previous.emoji).map(|(emoji, _)| emoji.clone());
+
+    let emoji = match &args.emoji {
+        Some(query) => match catalog.find(query) {
+            Some(emoji) => emoji.clone(),
+            None => return Err(Error::new(ErrorCode::NotFound, ErrorClass::Invalid, unknown_emoji_message(catalog, query))),
+        },
+        None if interactive => {
+            let preferred: Vec<String> = previous_emoji.iter().map(|emoji| emoji.shortcode.clone()).collect();
+            prompt_emoji(repo, config, catalog, &preferred)?
+        }
+        None => match previous_emoji {
+            Some(emoji) => emoji,
+            None => return Err(Error::from_str("missing required value for 'emoji' in non-interactive mode")),
+        },
+    };
+
+    // the scope is only asked for when the template can render it
+    let scope = match &args.scope {
+        Some(scope) => scope.trim().to_string(),
+        None if interactive && template.uses("scope") => {
+            let members = repo.workdir().map(workspace_members).unwrap_or_default();
+            let mut suggestions = suggest_scopes(&staged_changes(repo)?, &members);
+            if !previous.scope.is_empty() {
+                suggestions.retain(|scope| *scope != previous.scope);
+                suggestions.insert(0, previous.scope.clone());
+            }
+            let options = scope_options(&suggestions, config.scopes.as_deref());
+            select_scope(options, config.scopes.is_none())
+                .map_err(|e| Error::new(ErrorCode::NotFound, ErrorClass::Invalid, e.to_string()))?
+        }
+        None => previous.scope.clone(),
+    };
+    if let Some(allowed) = &config.scopes
+        && !scope.is_empty()
+        && !allowed.contains(&scope) {
+        return Err(Error::new(ErrorCode::Invalid, ErrorClass::Invalid, format!(
+            "scope '{}' is not allowed, expected one of: {}", scope, allowed.join(", ")
+        )));
+    }
+    let previous_breaking = previous.footers.iter()
+        .find(|(token, _)| token == BREAKING_CHANGE)
+        .map(|(_, value)| value.as_str())
+        .unwrap_or_default();
+    let mut title = resolve_input(&args.title, "Provide a commit title > ", interactive, true, &previous.subject)?;
+    // with the editor, the body and footers are written there instead
+    let prompt_rest = interactive && !args.edit;
+    let message = resolve_input(&args.body, "Provide a commit message > ", prompt_rest, false, &previous.body)?;
+    let breaking_changes = resolve_input(
+        &args.breaking, "Provide a breaking changes description > ", prompt_rest, false, previous_breaking
+    )?;
+
+    // the issue key of the branch, confirmed or edited when prompting; a
+    // protected branch asks for one even when none was detected
+    let branch = current_branch(repo).unwrap_or_default();
+    let detected = match &config.issue_pattern {
+        Some(pattern) => issue_from_branch(&branch, pattern)?.unwrap_or_default(),
+        None => String::new(),
+    };
+    let required = branch_matches(&branch, &config.require_issue_branches);
+    let issue = resolve_input(
+        &args.issue, "Provide the issue key > ", interactive && (required || !detected.is_empty()), false, &detected
+    )?;
+    if required && issue.is_empty() && args.refs.is_empty() && args.closes.is_empty() {
+        return Err(Error::new(ErrorCode::Invalid, ErrorClass::Invalid, format!(
+            "branch '{}' requires an issue key, pass one with --issue, --refs or --closes", branch
+        )));
+    }
+
+    // issue references, co-authors then the sign-off, like git trailers
+    let mut trailers: Vec<(String, String)> = Vec::new();
+    if !issue.is_empty() {
+        match config.issue_placement {
+            IssuePlacement::Subject if !mentions_issue(&title, &issue) => title = format!("{} {}", issue, title),
+            IssuePlacement::Subject => {}
+            IssuePlacement::Trailer => trailers.push((REFS.to_string(), issue_reference(&issue))),
+        }
+    }
+    trailers.extend(args.refs.iter().map(|issue| (REFS.to_string(), issue_reference(issue))));
+    trailers.extend(args.closes.iter().map(|issue| (CLOSES.to_string(), issue_reference(issue))));
+    if !args.co_authors.is_empty() || prompt_rest {
+        let known = known_authors(repo, identity)?;
+        let mut co_authors = args.co_authors.iter()
+            .map(|query| resolve_author(query, &known))
+            .collect::<Result<Vec<String>, Error>>()?;
+        if co_authors.is_empty() && !known.is_empty() {
+            co_authors = prompt_co_authors(&known)?;
+        }
+        trailers.extend(co_authors.into_iter().map(|co_author| (CO_AUTHORED_BY.to_string(), co_author)));
+    }
+    if args.signoff {
+        trailers.push((SIGNED_OFF_BY.to_string(), identity.to_string()));
+    }
+
+    // the command-line format wins over the configuration
+    let emoji_format = args.emoji_format.unwrap_or(config.emoji_format);
+
+    // the trailers join the breaking change footer in a single block, without
+    // repeating the ones an amended message already has
+    let mut footers: Vec<(String, String)> = Vec::new();
+    let all_footers = std::iter::once((BREAKING_CHANGE.to_string(), breaking_changes))
+        .chain(previous.footers.iter().filter(|(token, _)| token != BREAKING_CHANGE).cloned())
+        .chain(trailers);
+    for footer in all_footers {
+        if !footers.contains(&footer) {
+            footers.push(footer);
+        }
+    }
+
+    let parts = MessageParts {
+        emoji: emoji.render(emoji_format),
+        commit_type: emoji.conventional_type.clone().unwrap_or_default(),
+        scope,
+        subject: title,
+        body: message,
+        footers,
+    };
+    Ok((template.render(&parts), emoji))
+}
+
+const RECENT_COMMITS: usize = 20;
+
+// the commit a fixup or squash commit targets: the given revision, or one
+// picked among the recent commits of the branch
+fn autosquash_target<'r>(repo: &'r Repository, rev: &str, interactive: bool) -> Result<Commit<'r>, Error> {
+    if !rev.is_empty() {
+        return repo.revparse_single(rev)?.peel_to_commit();
+    }
+    if !interactive {
+        return Err(Error::from_str("missing the commit to fix up in non-interactive mode"));
+    }
+    if repo.head().is_err() {
+        return Err(Error::new(
+            ErrorCode::UnbornBranch, ErrorClass::Reference, "the current branch has no commit to fix up yet"
+        ));
+    }
+
+    let mut revwalk = repo.revwalk()?;
+    revwalk.push_head()?;
+    revwalk.simplify_first_parent()?;
+    let commits = revwalk.take(RECENT_COMMITS)
+        .map(|oid| repo.find_commit(oid?))
+        .collect::<Result<Vec<Commit>, Error>>()?;
+    let labels: Vec<String> = commits.iter()
+        .map(|commit| format!("{} {}", &commit.id().to_string()[..7], commit.summary().unwrap_or_default()))
+        .collect();
+
+    let picked = Select::new("Select the commit to fix up:", labels)
+        .raw_prompt()
+        .map_err(|e| Error::new(ErrorCode::NotFound, ErrorClass::Invalid, e.to_string()))?;
+    Ok(commits[picked.index].clone())
+}
+
+// `fixup! <subject>`, or `squash! <subject>` followed by the body, which
+// `git rebase --autosquash` folds into the target; the subject is kept as it
+// is, emoji included, so that it matches
+fn autosquash_message(target: &Commit, args: &CommitArgs, interactive: bool) -> Result<String, Error> {
+    let subject = target.summary().unwrap_or_default();
+    if args.fixup.is_some() {
+        return Ok(format!("fixup! {}\n", subject));
+    }
+    let body = resolve_input(&args.body, "Provide a commit message > ", interactive, false, "")?;
+    match body.is_empty() {
+        true => Ok(format!("squash! {}\n", subject)),
+        false => Ok(format!("squash! {}\n\n{}\n", subject, body)),
+    }
+}
+
+// points the current branch, or a detached HEAD, to a new commit, only if
+// it still points to `current` (`None` for a branch with no commit yet), so
+// that a commit made in the meantime is not lost
+fn update_head(repo: &Repository, oid: Oid, current: Option<Oid>, log_message: &str) -> Result<(), Error> {
+    let name = repo.find_reference("HEAD")?.symbolic_target().unwrap_or("HEAD").to_string();
+    let updated = match current {
+        Some(current) => repo.reference_matching(&name, oid, true, current, log_message),
+        None => repo.reference(&name, oid, false, log_message),
+    };
+    updated.map(|_| ()).map_err(|e| match e.code() {
+        ErrorCode::Modified | ErrorCode::Exists => Error::new(e.code(), ErrorClass::Reference, format!(
+            "cannot update {}: it changed while committing, the commit {} was not recorded", name, oid
+        )),
+        _ => e,
+    })
+}
+
+// refuses a commit whose tree would be the one of its parent (or, for a
+// root commit, empty), listing what could still be staged; the index is
+// compared without writing its tree, so that a dry run writes nothing
+fn check_not_empty(repo: &Repository, parent: Option<&Commit>) -> Result<(), Error> {
+    let mut index = repo.index()?;
+    index.read(true)?;
+    let base = parent.map(|parent| parent.tree()).transpose()?;
+    if repo.diff_tree_to_index(base.as_ref(), Some(&index), None)?.deltas().len() > 0 {
+        return Ok(());
+    }
+
+    let mut message = String::from("nothing to commit, no changes are staged (use --allow-empty to commit anyway)");
+    // a bare repository has no working directory to list
+    let unstaged = match repo.is_bare() {
+        true => Vec::new(),
+        false => unstaged_changes(repo)?,
+    };
+    if !unstaged.is_empty() {
+        message.push_str("\nchanges not staged for commit:");
+        for change in unstaged {
+            let status = match change.status {
+                Delta::Untracked => "untracked",
+                Delta::Deleted => "deleted",
+                Delta::Typechange => "typechange",
+                _ => "modified",
+            };
+            message.push_str(&format!("\n\t{:<12}{}", format!("{}:", status), change.path));
+        }
+    }
+    Err(Error::new(ErrorCode::Invalid, ErrorClass::Index, message))
+}
+
+// the widest `+`/`-` bar of a stat line, longer ones are scaled down
+const STAT_BAR_WIDTH: usize = 40;
+
+fn plural(count: usize, word: &str) -> String {
+    match count {
+        1 => format!("{} {}", count, word),
+        _ => format!("{} {}s", count, word),
+    }
+}
+
+// what `--dry-run` shows instead of committing: the identities, the parents,
+// the message and the staged files, like `git diff --stat`
+fn render_dry_run(message: &str, author: &Signature, committer: &Signature, parents: &[Commit], stats: &[FileStat]) -> String {
+    let identity = |signature: &Signature| format!(
+        "{} <{}> {}",
+        signature.name().unwrap_or_default(), signature.email().unwrap_or_default(), format_date(&signature.when())
+    );
+    let mut output = format!("Author:    {}\nCommitter: {}\n", identity(author), identity(committer));
+    if parents.is_empty() {
+        output.push_str("Parent:    (root commit)\n");
+    }
+    for parent in parents {
+        let id = parent.id().to_string();
+        output.push_str(&format!("Parent:    {} {}\n", &id[..7], parent.summary().unwrap_or_default()));
+    }
+    output.push('\n');
+    for line in message.lines() {
+        output.push_str(format!("    {}", line).trim_end());
+        output.push('\n');
+    }
+    output.push('\n');
+
+    if stats.is_empty() {
+        output.push_str(" no changes staged\n");
+        return output;
+    }
+    let paths: Vec<String> = stats.iter()
+        .map(|stat| match &stat.change.old_path {
+            Some(old_path) => format!("{} => {}", old_path, stat.change.path),
+            None => stat.change.path.clone(),
+        })
+        .collect();
+    let path_width = paths.iter().map(|path| path.chars().count()).max().unwrap_or_default();
+    let most = stats.iter().map(|stat| stat.insertions + stat.deletions).max().unwrap_or_default();
+    let count_width = most.to_string().len().max(3);
+    let scale = |lines: usize| match most > STAT_BAR_WIDTH && lines > 0 {
+        true => (lines * STAT_BAR_WIDTH / most).max(1),
+        false => lines,
+    };
+    for (stat, path) in stats.iter().zip(&paths) {
+        let status = match stat.change.status {
+            Delta::Added => 'A',
+            Delta::Deleted => 'D',
+            Delta::Renamed => 'R',
+            Delta::Copied => 'C',
+            Delta::Typechange => 'T',
+            _ => 'M',
+        };
+        let (count, bar) = match stat.binary {
+            true => ("Bin".to_string(), String::new()),
+            false => (
+                (stat.insertions + stat.deletions).to_string(),
+                format!("{}{}", "+".repeat(scale(stat.insertions)), "-".repeat(scale(stat.deletions))),
+            ),
+        };
+        let line = format!(" {} {:<path_width$} | {:>count_width$} {}", status, path, count, bar);
+        output.push_str(line.trim_end());
+        output.push('\n');
+    }
+    output.push_str(&format!(
+        " {} changed, {}(+), {}(-)\n",
+        plural(stats.len(), "file"),
+        plural(stats.iter().map(|stat| stat.insertions).sum(), "insertion"),
+        plural(stats.iter().map(|stat| stat.deletions).sum(), "deletion"),
+    ));
+    output
+}
+
 /// Creates a new commit on the current branch in the specified repository.
 ///
 /// This function creates and writes a commit object that captures the current state
 /// of the repository index (staging area) along with a commit message composed of
-/// an emoji, a user-provided description, and breaking changes notes. If no repository
+/// an emoji, a title, a body, and breaking changes notes, laid out by the
+/// configured message [`Template`]. Values supplied through
+/// `args` are used as they are; the missing ones are prompted for. If no repository
 /// is provided (`None`), it attempts to discover the current repository automatically.
 ///
 /// # Arguments
 ///
 /// * `repo` - An optional reference to a `Repository`. If `None`, the function
-///            attempts to find the current repository automatically.
+///   attempts to find the current repository automatically.
+/// * `args` - The command-line values for the message. When
+///   `args.no_interactive` is set, or the configuration disables prompts,
+///   nothing is prompted and a missing emoji or title is an error.
 ///
 /// # Errors
 ///
-/// Returns an `Err(String)` if:
+/// Returns an `Err(Error)` if:
 /// - The current repository cannot be determined.
-/// - Configuration values for username or email cannot be retrieved.
-/// - The emoji selection fails.
+/// - The author or committer identity is unknown in non-interactive mode,
+///   `args.author` matches no previous author, or `args.date` is invalid.
+/// - The glyphit configuration or its message template cannot be loaded.
+/// - The emoji catalog cannot be loaded.
+/// - The emoji selection fails, or the supplied emoji is not in the catalog.
+/// - A required value is missing in non-interactive mode.
+/// - The scope is not one of the configured `scopes`.
+/// - The fixup or squash target cannot be resolved, or is missing in
+///   non-interactive mode.
+/// - The branch requires an issue key and none was given or detected
+///   (fixup and squash commits are exempt).
+/// - A co-author is not a `Name <email>` identity and matches no previous
+///   author, or several.
+/// - No change is staged (the tree is the parent's) and `args.allow_empty`
+///   is not set.
+/// - The subject line exceeds the configured length limit.
+/// - The `pre-commit`, `prepare-commit-msg` or `commit-msg` hook or the
+///   editor fails, or the message is empty after they ran.
 /// - There are problems accessing the repository index or writing the tree.
 /// - The HEAD commit cannot be retrieved (in case of an existing commit).
-/// - Committing to the repository fails.
+/// - Committing to the repository fails, or the commit cannot be signed.
 ///
 /// # Returns
 ///
-/// * `Ok(0)` on successful commit.
+/// * `Ok(Oid)` with the id of the new commit, or the zero id after a dry run,
+///   which creates nothing.
 ///
 /// # Workflow
 ///
 /// - Gets the current or specified repository.
-/// - Reads user configuration for name and email.
-/// - Prompts the user for a commit message and breaking changes description.
-/// - Builds the commit message by prefixing it with an emoji.
+/// - Runs the `pre-commit` hook.
+/// - Refuses to go on when the index has the same tree as the parent commit
+///   (the one of `HEAD`, or its parent when amending), listing the unstaged
+///   changes, unless `args.allow_empty` is set.
+/// - Reads the glyphit [`Config`], and resolves the author and committer like
+///   git: `args.author` and `args.date`, the `GIT_AUTHOR_*` and
+///   `GIT_COMMITTER_*` variables, then `user.name` and `user.email`, prompted
+///   for (and optionally saved) when missing.
+/// - Loads the emoji catalog, restricted to the allowed emojis.
+/// - Resolves the emoji (the picker lists first the emojis suggested by the
+///   staged changes, then the ones the user picks most often), scope (when the template uses one), title, body and
+///   breaking changes description, prompting for the missing ones. The scope
+///   picker suggests the workspace members or top-level directories of the
+///   staged files, restricted to the configured `scopes` if any.
+/// - Builds the commit message with the configured template, rendering the
+///   emoji as a glyph, a shortcode or both according to `args.emoji_format`
+///   or the configured `emoji_format`. The `BREAKING CHANGE` footer is only
+///   added when a description was given. With `args.fixup` or `args.squash`,
+///   the message is instead `fixup! ` or `squash! ` followed by the subject
+///   of the target commit, picked among the recent commits when no revision
+///   is given.
+/// - Detects the issue key from the branch name with the configured
+///   `issue_pattern`, confirmed in a prompt, and writes it in the subject or a
+///   `Refs` trailer.
+/// - Appends the trailers requested by `args`: `Refs`, `Closes`,
+///   `Co-authored-by` (completed from the authors of the history, and
+///   prompted for in interactive mode) and `Signed-off-by`, after the
+///   breaking change footer.
+/// - Runs the `prepare-commit-msg` and `commit-msg` hooks on the message,
+///   written to `.git/COMMIT_EDITMSG`, and reads it back. With `args.edit`,
+///   the file also lists the staged files in comments and is opened in the
+///   editor in between; comment lines are then stripped.
+/// - Checks the subject line against the configured length limit.
 /// - Constructs the commit tree from the current index.
 /// - Retrieves the current `HEAD` commit as the parent (if any).
-/// - Creates a commit with the assembled information.
-pub fn commit(repo: Option<&Repository>, debug: bool) -> Result<Oid, Error> {
+/// - Creates a commit with the assembled information. When `commit.gpgsign`
+///   is set, the commit is signed with `gpg`, `gpgsm` or `ssh-keygen`
+///   according to `gpg.format` and `user.signingkey`, like git does.
+/// - In interactive mode, records the emoji in the usage history.
+/// - Runs the `post-commit` hook.
+///
+/// With `args.dry_run`, no hook, editor or signing program is run: the
+/// message is composed as above and printed with the author, committer,
+/// parents and a summary of the staged changes, and nothing is written.
+///
+/// Hooks are looked up like git does (honouring `core.hooksPath`);
+/// `args.no_verify` skips `pre-commit` and `commit-msg`.
+pub fn commit(repo: Option<&Repository>, args: &CommitArgs) -> Result<Oid, Error> {
     let owned_repo;
     let current_repo = match repo{
         Some(r) => r,
         _ => {
-            owned_repo = get_current_repository().map_err(|e| e)?;
+            owned_repo = get_current_repository()?;
             &owned_repo
         }
     };
-    let repo_configuration = match current_repo.config() {
-        Ok(config) => config,
-        Err(e) => return Err(e)
-    };
 
-    let mut commit_message = String::new();
-
-    if !debug {
-        let binding;
-        let emoji = match select_emoji() {
-            Ok(emo) => match emo.chars().next() {
-                Some(char) => {
-                    binding = char.to_string();
-                    binding.as_str()
-                }
-                _ => "",
-            },
-            Err(e) => return Err(Error::new(ErrorCode::NotFound, ErrorClass::Invalid, e.to_string()))
-        };
+    // amending replaces HEAD, so there must be one
+    let amended = match args.amend {
+        true => match current_repo.head() {
+            Ok(head) => Some(head.peel_to_commit()?),
+            Err(_) => return Err(Error::new(
+                ErrorCode::UnbornBranch, ErrorClass::Reference, "cannot amend: the current branch has no commit yet"
+            )),
+        },
+        false => None,
+    };
 
-        commit_message.push_str(emoji);
+    // like git, pre-commit runs first and may still change the index
+    if !args.no_verify && !args.dry_run {
+        run_hook(current_repo, "pre-commit", &[], &[])?;
+    }
 
-        let title = user_input("Provide a commit title > ".to_string());
-        commit_message.push_str(title.as_str());
-        commit_message.push_str("\n");
-        let message = user_input("Provide a commit message > ".to_string());
-        commit_message.push_str(message.as_str());
-        commit_message.push_str("\n");
-        let breaking_changes = user_input("Provide a breaking changes description > ".to_string());
-        commit_message.push_str(format!("BREAKING CHANGES: {}\n", breaking_changes).as_str());
-    } else {
-        commit_message.push_str("unit testing");
+    // get HEAD commit to set as parent; an amended commit keeps its parents
+    // and author
+    let parents: Vec<Commit> = match (&amended, current_repo.head()) {
+        (Some(head), _) => head.parents().collect(),
+        (None, Ok(head)) => vec![head.peel_to_commit()?],
+        (None, Err(_)) => vec![], // Unborn branch, so NO parent
+    };
+    // like git, an amended merge may keep the tree of its first parent
+    if !args.allow_empty && parents.len() <= 1 {
+        check_not_empty(current_repo, parents.first())?;
     }
 
-    let name = repo_configuration.get_string("user.name")?.to_string();
-    let email = repo_configuration.get_string("user.email")?.to_string();
+    let config = Config::load(current_repo)?;
 
-    // get index and write tree
-    let mut index = match current_repo.index() {
-        Ok(idx) => idx,
-        Err(e) => return Err(e)
-    };
-    let tree_oid = match index.write_tree() {
-        Ok(oid) => oid,
-        Err(e) => return Err(e)
+    let interactive = !args.no_interactive && config.interactive;
+
+    let catalog = EmojiCatalog::load(&config)?;
+
+    // when amending, the parts of the current message are the defaults
+    let previous = amended.as_ref()
+        .map(|head| MessageParts::parse(head.message().unwrap_or_default(), &catalog))
+        .unwrap_or_default();
+    let target = match args.fixup.as_ref().or(args.squash.as_ref()) {
+        Some(rev) => Some(autosquash_target(current_repo, rev, interactive)?),
+        None => None,
     };
-    let tree = match current_repo.find_tree(tree_oid) {
-        Ok(idx_tree) => idx_tree,
-        Err(e) => return Err(e)
+    // an amended commit keeps its author, unless overridden
+    let previous_author = amended.as_ref().map(|head| head.author());
+    let identities = resolve_identities(
+        current_repo, args.author.as_deref(), args.date.as_deref(), previous_author.as_ref(), interactive
+    )?;
+    let (author, committer) = (&identities.author, &identities.committer);
+    let identity = format!("{} <{}>", committer.name().unwrap_or_default(), committer.email().unwrap_or_default());
+
+    // fixup and squash commits take no issue key, even on a branch requiring
+    // one: they are folded into their target, which has its own
+    let (commit_message, emoji) = match &target {
+        Some(target) => (autosquash_message(target, args, interactive && !args.edit)?, None),
+        None => {
+            let (message, emoji) = compose_message(current_repo, &config, &catalog, args, interactive, &previous, &identity)?;
+            (message, Some(emoji))
+        }
     };
 
-    // get HEAD commit to set as parent
-    let parents = match current_repo.head() {
-        Ok(head) => {
-            match head.peel_to_commit() {
-                Ok(commit) => vec![commit],
-                Err(e) => return Err(e),
+    // the message goes through prepare-commit-msg, the editor and commit-msg,
+    // which may all change it; a dry run leaves COMMIT_EDITMSG alone
+    let commit_message = match args.dry_run {
+        true => clean_message(&commit_message, None),
+        false => {
+            let comment_char = comment_char(current_repo)?;
+            let message_file = current_repo.path().join("COMMIT_EDITMSG");
+            let message_path = message_file.to_string_lossy().to_string();
+            let io_error = |e: std::io::Error| Error::from_str(format!("cannot access {}: {}", message_path, e).as_str());
+            let content = match args.edit {
+                true => edit_template(&commit_message, &staged_changes(current_repo)?, comment_char),
+                false => commit_message,
+            };
+            std::fs::write(&message_file, &content).map_err(io_error)?;
+            let no_editor = [("GIT_EDITOR", ":")];
+            run_hook(current_repo, "prepare-commit-msg", &[&message_path, "message"], &no_editor)?;
+            if args.edit {
+                edit_file(current_repo, &message_file)?;
+            }
+            if !args.no_verify {
+                run_hook(current_repo, "commit-msg", &[&message_path], &no_editor)?;
             }
+            clean_message(
+                &std::fs::read_to_string(&message_file).map_err(io_error)?,
+                args.edit.then_some(comment_char),
+            )
         }
-        Err(_) => vec![], // Unborn branch, so NO parent
     };
+    if commit_message.is_empty() {
+        return Err(Error::from_str("aborting commit due to empty commit message"));
+    }
+
+    let subject_length = strip_autosquash(commit_message.lines().next().unwrap_or_default()).chars().count();
+    if subject_length > config.subject_max_length {
+        return Err(Error::new(ErrorCode::Invalid, ErrorClass::Invalid, format!(
+            "the subject line is {} characters long, the limit is {}", subject_length, config.subject_max_length
+        )));
+    }
+
+    if args.dry_run {
+        // the changes are the ones the commit would record, from its first parent
+        let base = parents.first().map(|parent| parent.tree()).transpose()?;
+        let stats = staged_stats(current_repo, base.as_ref())?;
+        print!("{}", render_dry_run(&commit_message, author, committer, &parents, &stats));
+        return Ok(Oid::zero());
+    }
 
-    let signature = Signature::now(&name, &email)?;
+    // get index (re-read, as hooks may have staged changes) and write tree
+    let mut index = current_repo.index()?;
+    index.read(true)?;
+    let tree_oid = index.write_tree()?;
+    let tree = current_repo.find_tree(tree_oid)?;
 
     let parent_refs: Vec<&git2::Commit> = parents.iter().collect();
-    current_repo.commit(
-        Some("HEAD"),
-        &signature,
-        &signature,
-        &(commit_message.as_str()),
-        &tree,
-        &parent_refs,
-    )
+    let oid = match Signer::from_config(current_repo, &identity)? {
+        // `Repository::commit` cannot sign: the commit object is built, signed
+        // and written separately, then HEAD is moved to it
+        Some(signer) => {
+            let buffer = current_repo.commit_create_buffer(
+                author, committer, commit_message.as_str(), &tree, &parent_refs
+            )?;
+            let content = buffer.as_str()
+                .ok_or_else(|| Error::from_str("the commit to sign is not valid UTF-8"))?;
+            let oid = current_repo.commit_signed(content, &signer.sign(content)?, None)?;
+            let kind = match (&amended, parents.is_empty()) {
+                (Some(_), _) => "commit (amend)",
+                (None, true) => "commit (initial)",
+                (None, false) => "commit",
+            };
+            let subject = commit_message.lines().next().unwrap_or_default();
+            let current = amended.as_ref().or(parents.first()).map(|commit| commit.id());
+            update_head(current_repo, oid, current, &format!("{}: {}", kind, subject))?;
+            oid
+        }
+        None => match &amended {
+            Some(head) => head.amend(
+                Some("HEAD"), Some(author), Some(committer), None, Some(commit_message.as_str()), Some(&tree)
+            )?,
+            None => current_repo.commit(
+                Some("HEAD"),
+                author,
+                committer,
+                commit_message.as_str(),
+                &tree,
+                &parent_refs,
+            )?,
+        },
+    };
+
+    // scripted commits do not count towards the picker ranking, and a broken
+    // state file must not fail a commit that has already been written
+    if interactive
+        && let Some(emoji) = &emoji
+        && let Err(e) = record_usage(config.history, &emoji.shortcode) {
+        eprintln!("warning: cannot record emoji usage: {}", e.message());
+    }
+
+    // the commit exists at this point: a failing post-commit hook only warns
+    if let Err(e) = run_hook(current_repo, "post-commit", &[], &[]) {
+        eprintln!("warning: {}", e.message());
+    }
+    if let Some(head) = &amended {
+        let rewritten = format!("{} {}\n", head.id(), oid);
+        if let Err(e) = run_hook_with_input(current_repo, "post-rewrite", &["amend"], &[], Some(&rewritten)) {
+            eprintln!("warning: {}", e.message());
+        }
+    }
+    Ok(oid)
 }
 
 #[cfg(test)]
@@ -221,7 +727,18 @@ mod tests {
     use std::fs::File;
     use tempfile::tempdir;
     use crate::functions::add::add;
+    use crate::types::emoji::EmojiFormat;
 
+    // most tests are about the message, and commit without staging anything
+    fn non_interactive(emoji: &str, title: &str) -> CommitArgs {
+        CommitArgs {
+            emoji: Some(emoji.to_string()),
+            title: Some(title.to_string()),
+            no_interactive: true,
+            allow_empty: true,
+            ..Default::default()
+        }
+    }
 
     #[test]
     fn test_commit() {
@@ -231,25 +748,707 @@ mod tests {
         let file_path = temp_dir.path().join("initial.txt");
         File::create(&file_path).unwrap();
 
-        add(&vec!["initial.txt".to_string()], Some(&repo)).unwrap();
+        add(&["initial.txt".to_string()], Some(&repo)).unwrap();
 
         let mut config = repo.config().unwrap();
         config.set_str("user.name", "Test User").unwrap();
         config.set_str("user.email", "test@example.com").unwrap();
 
-        let tree;
         let signature = Signature::now("Test User", "test@example.com").unwrap();
         let mut index = repo.index().unwrap();
         let tree_id = index.write_tree().unwrap();
-        tree = repo.find_tree(tree_id).unwrap();
+        let tree = repo.find_tree(tree_id).unwrap();
         repo.commit(Some("HEAD"), &signature, &signature, "Initial commit", &tree, &[]).unwrap();
 
-        let result = commit(Some(&repo), true);
+        let args = CommitArgs {
+            body: Some("unit testing body".to_string()),
+            breaking: Some("none".to_string()),
+            ..non_interactive(":bug:", "unit testing")
+        };
+        let result = commit(Some(&repo), &args);
 
         assert!(result.is_ok());
 
         let head = repo.head().unwrap();
         let commit = head.peel_to_commit().unwrap();
-        assert_eq!(commit.message().unwrap(), "unit testing");
+        assert_eq!(commit.message().unwrap(), "🐛 unit testing\n\nunit testing body\n\nBREAKING CHANGE: none\n");
+        assert_eq!(commit.parent_count(), 1);
+    }
+
+    #[test]
+    fn test_commit_emoji_by_glyph() {
+        let temp_dir = tempdir().unwrap();
+        let repo = Repository::init(temp_dir.path()).unwrap();
+
+        let mut config = repo.config().unwrap();
+        config.set_str("user.name", "Test User").unwrap();
+        config.set_str("user.email", "test@example.com").unwrap();
+
+        commit(Some(&repo), &non_interactive("✨", "first")).unwrap();
+
+        let commit = repo.head().unwrap().peel_to_commit().unwrap();
+        assert_eq!(commit.message().unwrap(), "✨ first\n");
+    }
+
+    #[test]
+    fn test_commit_every_catalog_emoji() {
+        let temp_dir = tempdir().unwrap();
+        let repo = Repository::init(temp_dir.path()).unwrap();
+
+        let mut config = repo.config().unwrap();
+        config.set_str("user.name", "Test User").unwrap();
+        config.set_str("user.email", "test@example.com").unwrap();
+
+        for (i, emoji) in EmojiCatalog::builtin().emojis().iter().enumerate() {
+            // stage a distinct change for every commit
+            let file_name = format!("file{}.txt", i);
+            std::fs::write(temp_dir.path().join(&file_name), &emoji.shortcode).unwrap();
+            add(&[file_name], Some(&repo)).unwrap();
+
+            let oid = commit(Some(&repo), &non_interactive(&emoji.shortcode, "round trip")).unwrap();
+
+            let stored = repo.find_commit(oid).unwrap();
+            let message = stored.message().unwrap();
+            assert!(
+                message.starts_with(&emoji.glyph),
+                "{:?} does not start with {:?}", message, emoji.glyph
+            );
+            assert_eq!(&message[emoji.glyph.len()..], " round trip\n");
+        }
+    }
+
+    #[test]
+    fn test_commit_keeps_multi_codepoint_glyphs() {
+        let temp_dir = tempdir().unwrap();
+        let repo = Repository::init(temp_dir.path()).unwrap();
+
+        let mut config = repo.config().unwrap();
+        config.set_str("user.name", "Test User").unwrap();
+        config.set_str("user.email", "test@example.com").unwrap();
+
+        // text-presentation input still yields the fully-qualified glyph
+        for (query, glyph) in [("🚑", "🚑\u{FE0F}"), ("⬇", "⬇\u{FE0F}"), ("♻", "♻\u{FE0F}"),
+                               ("🏗", "🏗\u{FE0F}"), ("🧑\u{200D}💻", "🧑\u{200D}💻")] {
+            let oid = commit(Some(&repo), &non_interactive(query, "glyph")).unwrap();
+            let message = repo.find_commit(oid).unwrap().message().unwrap().to_string();
+            assert_eq!(message.strip_suffix(" glyph\n"), Some(glyph));
+        }
+    }
+
+    #[test]
+    fn test_commit_emoji_format() {
+        let temp_dir = tempdir().unwrap();
+        let repo = Repository::init(temp_dir.path()).unwrap();
+
+        let mut config = repo.config().unwrap();
+        config.set_str("user.name", "Test User").unwrap();
+        config.set_str("user.email", "test@example.com").unwrap();
+        config.set_str("glyphit.emojiFormat", "shortcode").unwrap();
+
+        let oid = commit(Some(&repo), &non_interactive("bug", "from config")).unwrap();
+        assert!(repo.find_commit(oid).unwrap().message().unwrap().starts_with(":bug: from config\n"));
+
+        let args = CommitArgs {
+            emoji_format: Some(EmojiFormat::Both),
+            ..non_interactive("bug", "from flag")
+        };
+        let oid = commit(Some(&repo), &args).unwrap();
+        assert!(repo.find_commit(oid).unwrap().message().unwrap().starts_with("🐛 :bug: from flag\n"));
+    }
+
+    #[test]
+    fn test_commit_custom_template() {
+        let temp_dir = tempdir().unwrap();
+        let repo = Repository::init(temp_dir.path()).unwrap();
+
+        let mut config = repo.config().unwrap();
+        config.set_str("user.name", "Test User").unwrap();
+        config.set_str("user.email", "test@example.com").unwrap();
+        config.set_str("glyphit.template", "{subject} {emoji}\n\n{footers}").unwrap();
+
+        let args = CommitArgs {
+            body: Some("dropped".to_string()),
+            breaking: Some("kept".to_string()),
+            ..non_interactive("bug", "custom")
+        };
+        let oid = commit(Some(&repo), &args).unwrap();
+
+        assert_eq!(repo.find_commit(oid).unwrap().message().unwrap(), "custom 🐛\n\nBREAKING CHANGE: kept\n");
+    }
+
+    #[test]
+    fn test_commit_conventional_template() {
+        let temp_dir = tempdir().unwrap();
+        let repo = Repository::init(temp_dir.path()).unwrap();
+
+        let mut config = repo.config().unwrap();
+        config.set_str("user.name", "Test User").unwrap();
+        config.set_str("user.email", "test@example.com").unwrap();
+        config.set_str("glyphit.template", "conventional").unwrap();
+
+        let args = CommitArgs {
+            scope: Some("auth".to_string()),
+            ..non_interactive("sparkles", "add login form")
+        };
+        let oid = commit(Some(&repo), &args).unwrap();
+        assert_eq!(repo.find_commit(oid).unwrap().message().unwrap(), "✨ feat(auth): add login form\n");
+
+        let args = CommitArgs {
+            breaking: Some("sessions are invalidated".to_string()),
+            ..non_interactive("memo", "rewrite the guide")
+        };
+        let oid = commit(Some(&repo), &args).unwrap();
+        assert_eq!(
+            repo.find_commit(oid).unwrap().message().unwrap(),
+            "📝 docs!: rewrite the guide\n\nBREAKING CHANGE: sessions are invalidated\n"
+        );
+    }
+
+    #[test]
+    fn test_commit_amend() {
+        let temp_dir = tempdir().unwrap();
+        let repo = Repository::init(temp_dir.path()).unwrap();
+
+        let mut config = repo.config().unwrap();
+        config.set_str("user.name", "Test User").unwrap();
+        config.set_str("user.email", "test@example.com").unwrap();
+        config.set_str("glyphit.template", "conventional").unwrap();
+
+        let amend = CommitArgs { amend: true, no_interactive: true, ..Default::default() };
+        assert_eq!(commit(Some(&repo), &amend).unwrap_err().code(), ErrorCode::UnbornBranch);
+
+        let base = commit(Some(&repo), &non_interactive("tada", "begin a project")).unwrap();
+        let original = repo.find_commit(base).unwrap();
+        let args = CommitArgs {
+            scope: Some("auth".to_string()),
+            body: Some("with a password field".to_string()),
+            breaking: Some("sessions are invalidated".to_string()),
+            ..non_interactive("sparkles", "add login form")
+        };
+        let head = repo.find_commit(commit(Some(&repo), &args).unwrap()).unwrap();
+
+        File::create(temp_dir.path().join("login.rs")).unwrap();
+        add(&["login.rs".to_string()], Some(&repo)).unwrap();
+        config.set_str("user.name", "Other User").unwrap();
+
+        // the message is kept as it is, the staged file is added
+        let oid = commit(Some(&repo), &amend).unwrap();
+        let amended = repo.find_commit(oid).unwrap();
+        assert_eq!(amended.message(), head.message());
+        assert_eq!(amended.parent_ids().collect::<Vec<_>>(), vec![original.id()]);
+        assert_eq!(amended.author().name(), Some("Test User"));
+        assert_eq!(amended.committer().name(), Some("Other User"));
+        assert!(amended.tree().unwrap().get_name("login.rs").is_some());
+        assert_eq!(repo.head().unwrap().target(), Some(oid));
+
+        // flags override single parts
+        let args = CommitArgs { emoji: Some("bug".to_string()), title: Some("fix login form".to_string()), ..amend };
+        let oid = commit(Some(&repo), &args).unwrap();
+        assert_eq!(
+            repo.find_commit(oid).unwrap().message().unwrap(),
+            "🐛 fix(auth)!: fix login form\n\nwith a password field\n\nBREAKING CHANGE: sessions are invalidated\n"
+        );
+        assert_eq!(repo.find_commit(oid).unwrap().parent_ids().collect::<Vec<_>>(), vec![original.id()]);
+    }
+
+    #[test]
+    fn test_commit_fixup_and_squash() {
+        let temp_dir = tempdir().unwrap();
+        let repo = Repository::init(temp_dir.path()).unwrap();
+
+        let mut config = repo.config().unwrap();
+        config.set_str("user.name", "Test User").unwrap();
+        config.set_str("user.email", "test@example.com").unwrap();
+        config.set_i64("glyphit.subjectMaxLength", 20).unwrap();
+
+        let fixup = CommitArgs { fixup: Some("HEAD".to_string()), no_interactive: true, allow_empty: true, ..Default::default() };
+        assert!(commit(Some(&repo), &fixup).is_err());
+
+        let target = commit(Some(&repo), &non_interactive("bug", "fix a long crash")).unwrap();
+        commit(Some(&repo), &non_interactive("memo", "document it")).unwrap();
+
+        // the subject limit applies to the subject of the target
+        let args = CommitArgs { fixup: Some("HEAD~1".to_string()), ..fixup.clone() };
+        let oid = commit(Some(&repo), &args).unwrap();
+        assert_eq!(repo.find_commit(oid).unwrap().message().unwrap(), "fixup! 🐛 fix a long crash\n");
+
+        let args = CommitArgs {
+            squash: Some(target.to_string()),
+            body: Some("also handle empty input".to_string()),
+            no_interactive: true,
+            allow_empty: true,
+            ..Default::default()
+        };
+        let oid = commit(Some(&repo), &args).unwrap();
+        assert_eq!(
+            repo.find_commit(oid).unwrap().message().unwrap(),
+            "squash! 🐛 fix a long crash\n\nalso handle empty input\n"
+        );
+
+        let args = CommitArgs { fixup: Some(String::new()), ..fixup };
+        assert!(commit(Some(&repo), &args).is_err());
+    }
+
+    #[test]
+    fn test_commit_trailers() {
+        let temp_dir = tempdir().unwrap();
+        let repo = Repository::init(temp_dir.path()).unwrap();
+
+        let mut config = repo.config().unwrap();
+        config.set_str("user.name", "Alice Smith").unwrap();
+        config.set_str("user.email", "alice@example.com").unwrap();
+        commit(Some(&repo), &non_interactive("tada", "begin a project")).unwrap();
+        config.set_str("user.name", "Test User").unwrap();
+        config.set_str("user.email", "test@example.com").unwrap();
+
+        let args = CommitArgs {
+            breaking: Some("the API changed".to_string()),
+            signoff: true,
+            co_authors: vec!["smith".to_string(), "Bob <bob@example.com>".to_string()],
+            closes: vec!["12".to_string()],
+            refs: vec!["PROJ-7".to_string()],
+            ..non_interactive("bug", "fix crash")
+        };
+        let oid = commit(Some(&repo), &args).unwrap();
+        let message = "🐛 fix crash\n\nBREAKING CHANGE: the API changed\nRefs: PROJ-7\nCloses #12\n\
+            Co-authored-by: Alice Smith <alice@example.com>\nCo-authored-by: Bob <bob@example.com>\n\
+            Signed-off-by: Test User <test@example.com>\n";
+        assert_eq!(repo.find_commit(oid).unwrap().message().unwrap(), message);
+
+        // amending keeps the trailers, without repeating the sign-off
+        let args = CommitArgs { amend: true, signoff: true, no_interactive: true, allow_empty: true, ..Default::default() };
+        let oid = commit(Some(&repo), &args).unwrap();
+        assert_eq!(repo.find_commit(oid).unwrap().message().unwrap(), message);
+
+        let args = CommitArgs { co_authors: vec!["nobody".to_string()], ..non_interactive("bug", "fix crash") };
+        assert!(commit(Some(&repo), &args).is_err());
+
+        // a template without `{footers}` still gets the trailers
+        config.set_str("glyphit.template", "{emoji} {subject}").unwrap();
+        let args = CommitArgs { signoff: true, ..non_interactive("memo", "document it") };
+        let oid = commit(Some(&repo), &args).unwrap();
+        assert_eq!(
+            repo.find_commit(oid).unwrap().message().unwrap(),
+            "📝 document it\n\nSigned-off-by: Test User <test@example.com>\n"
+        );
+    }
+
+    #[test]
+    fn test_commit_issue_from_branch() {
+        let temp_dir = tempdir().unwrap();
+        let repo = Repository::init(temp_dir.path()).unwrap();
+        repo.set_head("refs/heads/feature/PROJ-421-login").unwrap();
+
+        let mut config = repo.config().unwrap();
+        config.set_str("user.name", "Test User").unwrap();
+        config.set_str("user.email", "test@example.com").unwrap();
+        config.set_str("glyphit.issuePattern", "[A-Z][A-Z0-9]+-[0-9]+").unwrap();
+        config.set_str("glyphit.requireIssueBranches", "feature/*").unwrap();
+
+        let oid = commit(Some(&repo), &non_interactive("sparkles", "add login form")).unwrap();
+        assert_eq!(repo.find_commit(oid).unwrap().message().unwrap(), "✨ add login form\n\nRefs: PROJ-421\n");
+
+        config.set_str("glyphit.issuePlacement", "subject").unwrap();
+        let args = CommitArgs { issue: Some("PROJ-7".to_string()), ..non_interactive("bug", "fix login form") };
+        let oid = commit(Some(&repo), &args).unwrap();
+        assert_eq!(repo.find_commit(oid).unwrap().message().unwrap(), "🐛 PROJ-7 fix login form\n");
+
+        // the key is not repeated when the subject has it already
+        let oid = commit(Some(&repo), &non_interactive("bug", "PROJ-421 fix it again")).unwrap();
+        assert_eq!(repo.find_commit(oid).unwrap().message().unwrap(), "🐛 PROJ-421 fix it again\n");
+        // a longer key is a different issue
+        let args = CommitArgs { issue: Some("PROJ-42".to_string()), ..non_interactive("bug", "follow PROJ-421 up") };
+        let oid = commit(Some(&repo), &args).unwrap();
+        assert_eq!(repo.find_commit(oid).unwrap().message().unwrap(), "🐛 PROJ-42 follow PROJ-421 up\n");
+
+        let args = CommitArgs { issue: Some(String::new()), ..non_interactive("bug", "fix crash") };
+        assert!(commit(Some(&repo), &args).is_err());
+        let args = CommitArgs { issue: Some(String::new()), closes: vec!["12".to_string()], ..args };
+        assert!(commit(Some(&repo), &args).is_ok());
+
+        // fixups are folded into a commit that has the key already
+        let head = repo.head().unwrap().peel_to_commit().unwrap();
+        repo.branch("feature/login", &head, false).unwrap();
+        repo.set_head("refs/heads/feature/login").unwrap();
+        assert!(commit(Some(&repo), &non_interactive("bug", "fix crash")).is_err());
+        let args = CommitArgs { fixup: Some("HEAD".to_string()), no_interactive: true, allow_empty: true, ..Default::default() };
+        let oid = commit(Some(&repo), &args).unwrap();
+        assert_eq!(repo.find_commit(oid).unwrap().message().unwrap(), "fixup! 🐛 fix crash\n");
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_commit_author_and_date() {
+        let temp_dir = tempdir().unwrap();
+        let repo = Repository::init(temp_dir.path()).unwrap();
+
+        let mut config = repo.config().unwrap();
+        config.set_str("user.name", "Test User").unwrap();
+        config.set_str("user.email", "test@example.com").unwrap();
+
+        let args = CommitArgs {
+            author: Some("Alice Smith <alice@example.com>".to_string()),
+            date: Some("2005-04-07T22:13:13+02:00".to_string()),
+            ..non_interactive("tada", "begin a project")
+        };
+        let head = repo.find_commit(commit(Some(&repo), &args).unwrap()).unwrap();
+        assert_eq!(head.author().name(), Some("Alice Smith"));
+        assert_eq!(head.author().when().seconds(), 1112904793);
+        assert_eq!(head.committer().name(), Some("Test User"));
+
+        // amending keeps the author, a pattern finds a previous one
+        let args = CommitArgs { amend: true, no_interactive: true, allow_empty: true, ..Default::default() };
+        let amended = repo.find_commit(commit(Some(&repo), &args).unwrap()).unwrap();
+        assert_eq!(amended.author().email(), Some("alice@example.com"));
+        assert_eq!(amended.author().when().seconds(), 1112904793);
+
+        let args = CommitArgs { author: Some("smith".to_string()), ..non_interactive("bug", "fix crash") };
+        let oid = commit(Some(&repo), &args).unwrap();
+        assert_eq!(repo.find_commit(oid).unwrap().author().name(), Some("Alice Smith"));
+
+        let args = CommitArgs { date: Some("last tuesday".to_string()), ..non_interactive("bug", "fix crash") };
+        assert!(commit(Some(&repo), &args).is_err());
+    }
+
+    #[test]
+    fn test_commit_refuses_empty() {
+        let temp_dir = tempdir().unwrap();
+        let repo = Repository::init(temp_dir.path()).unwrap();
+
+        let mut config = repo.config().unwrap();
+        config.set_str("user.name", "Test User").unwrap();
+        config.set_str("user.email", "test@example.com").unwrap();
+        let args = CommitArgs { allow_empty: false, ..non_interactive("tada", "begin a project") };
+
+        std::fs::write(temp_dir.path().join("notes.txt"), "todo\n").unwrap();
+        let error = commit(Some(&repo), &args).unwrap_err();
+        assert_eq!(error.class(), ErrorClass::Index);
+        assert!(error.message().contains("--allow-empty"));
+        assert!(error.message().ends_with("\n\tuntracked:  notes.txt"));
+
+        add(&["notes.txt".to_string()], Some(&repo)).unwrap();
+        commit(Some(&repo), &args).unwrap();
+
+        // nothing staged since the last commit
+        std::fs::write(temp_dir.path().join("notes.txt"), "done\n").unwrap();
+        let error = commit(Some(&repo), &args).unwrap_err();
+        assert!(error.message().ends_with("\n\tmodified:   notes.txt"));
+        let head = repo.head().unwrap().target().unwrap();
+
+        let oid = commit(Some(&repo), &CommitArgs { allow_empty: true, ..args.clone() }).unwrap();
+        let empty = repo.find_commit(oid).unwrap();
+        assert_eq!(empty.tree_id(), empty.parent(0).unwrap().tree_id());
+        assert_eq!(empty.parent_id(0).unwrap(), head);
+
+        // amending the empty commit without changes would leave it empty
+        let amend = CommitArgs { amend: true, ..args };
+        assert!(commit(Some(&repo), &amend).is_err());
+        add(&["notes.txt".to_string()], Some(&repo)).unwrap();
+        commit(Some(&repo), &amend).unwrap();
+    }
+
+    #[test]
+    fn test_commit_dry_run() {
+        let temp_dir = tempdir().unwrap();
+        let repo = Repository::init(temp_dir.path()).unwrap();
+
+        let mut config = repo.config().unwrap();
+        config.set_str("user.name", "Test User").unwrap();
+        config.set_str("user.email", "test@example.com").unwrap();
+        // hooks are not run by a dry run
+        std::fs::create_dir_all(temp_dir.path().join("hooks")).unwrap();
+        std::fs::write(temp_dir.path().join("hooks/pre-commit"), "#!/bin/sh\nexit 1\n").unwrap();
+        config.set_str("core.hooksPath", "hooks").unwrap();
+
+        std::fs::write(temp_dir.path().join("lib.rs"), "fn main() {}\n").unwrap();
+        add(&["lib.rs".to_string()], Some(&repo)).unwrap();
+        let objects = || {
+            let mut count = 0;
+            repo.odb().unwrap().foreach(|_| { count += 1; true }).unwrap();
+            count
+        };
+        let before = objects();
+
+        // the empty commit check runs too, without writing the tree
+        let args = CommitArgs { dry_run: true, allow_empty: false, ..non_interactive("tada", "begin a project") };
+        assert_eq!(commit(Some(&repo), &args).unwrap(), Oid::zero());
+        assert!(repo.head().is_err());
+        assert!(!repo.path().join("COMMIT_EDITMSG").exists());
+        assert_eq!(objects(), before);
+
+        let mut index = repo.index().unwrap();
+        index.remove_path(std::path::Path::new("lib.rs")).unwrap();
+        index.write().unwrap();
+        assert!(commit(Some(&repo), &args).is_err());
+        assert_eq!(objects(), before);
+    }
+
+    #[test]
+    fn test_render_dry_run() {
+        let temp_dir = tempdir().unwrap();
+        let repo = Repository::init(temp_dir.path()).unwrap();
+        let signature = Signature::new("Test User", "test@example.com", &git2::Time::new(1112904793, 120)).unwrap();
+        let tree = repo.find_tree(repo.index().unwrap().write_tree().unwrap()).unwrap();
+        let parent = repo.commit(None, &signature, &signature, "🎉 begin a project\n", &tree, &[]).unwrap();
+        let parent = repo.find_commit(parent).unwrap();
+
+        let stat = |path: &str, status, insertions, deletions, binary| FileStat {
+            change: crate::types::staged::StagedChange { path: path.to_string(), old_path: None, status },
+            insertions,
+            deletions,
+            binary,
+        };
+        let stats = vec![
+            stat("src/lib.rs", Delta::Modified, 3, 1, false),
+            stat("logo.png", Delta::Added, 0, 0, true),
+            stat("old.txt", Delta::Deleted, 0, 80, false),
+        ];
+        let id = parent.id().to_string();
+        let output = render_dry_run("✨ add login\n\nWith a form.\n", &signature, &signature, &[parent], &stats);
+        assert_eq!(output, format!(
+            "Author:    Test User <test@example.com> 2005-04-07 22:13:13 +0200\n\
+             Committer: Test User <test@example.com> 2005-04-07 22:13:13 +0200\n\
+             Parent:    {} 🎉 begin a project\n\
+             \n    ✨ add login\n\n    With a form.\n\n \
+             M src/lib.rs |   4 +-\n \
+             A logo.png   | Bin\n \
+             D old.txt    |  80 ----------------------------------------\n \
+             3 files changed, 3 insertions(+), 81 deletions(-)\n",
+            &id[..7]
+        ));
+
+        let output = render_dry_run("🎉 begin\n", &signature, &signature, &[], &[]);
+        assert!(output.contains("Parent:    (root commit)\n"));
+        assert!(output.ends_with(" no changes staged\n"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_commit_signed_with_ssh() {
+        use std::process::{Command, Stdio};
+
+        let temp_dir = tempdir().unwrap();
+        let key_dir = tempdir().unwrap();
+        let repo = Repository::init(temp_dir.path()).unwrap();
+        let key = key_dir.path().join("key");
+        match Command::new("ssh-keygen").args(["-q", "-t", "ed25519", "-N", "", "-f"]).arg(&key).status() {
+            Ok(status) => assert!(status.success()),
+            Err(_) => return, // no ssh-keygen to sign with
+        }
+
+        let mut config = repo.config().unwrap();
+        config.set_str("user.name", "Test User").unwrap();
+        config.set_str("user.email", "test@example.com").unwrap();
+        config.set_bool("commit.gpgsign", true).unwrap();
+        config.set_str("gpg.format", "ssh").unwrap();
+        config.set_str("user.signingkey", key.to_str().unwrap()).unwrap();
+
+        let base = commit(Some(&repo), &non_interactive("tada", "begin a project")).unwrap();
+        let oid = commit(Some(&repo), &non_interactive("bug", "fix crash")).unwrap();
+        assert_eq!(repo.head().unwrap().target(), Some(oid));
+        assert_eq!(repo.find_commit(oid).unwrap().parent_ids().collect::<Vec<_>>(), vec![base]);
+
+        let args = CommitArgs { amend: true, title: Some("fix the crash".to_string()), no_interactive: true, allow_empty: true, ..Default::default() };
+        let oid = commit(Some(&repo), &args).unwrap();
+        assert_eq!(repo.head().unwrap().target(), Some(oid));
+        assert_eq!(repo.find_commit(oid).unwrap().parent_ids().collect::<Vec<_>>(), vec![base]);
+
+        let (signature, signed_data) = repo.extract_signature(&oid, None).unwrap();
+        let signature_file = key_dir.path().join("commit.sig");
+        std::fs::write(&signature_file, &*signature).unwrap();
+        let mut check = Command::new("ssh-keygen")
+            .args(["-Y", "check-novalidate", "-n", "git", "-s"])
+            .arg(&signature_file)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .spawn()
+            .unwrap();
+        check.stdin.take().unwrap().write_all(&signed_data).unwrap();
+        assert!(check.wait().unwrap().success());
+
+        config.set_str("user.signingkey", key_dir.path().join("missing").to_str().unwrap()).unwrap();
+        assert!(commit(Some(&repo), &non_interactive("bug", "unsigned")).is_err());
+        assert_eq!(repo.head().unwrap().target(), Some(oid));
+    }
+
+    #[test]
+    fn test_update_head() {
+        let temp_dir = tempdir().unwrap();
+        let repo = Repository::init(temp_dir.path()).unwrap();
+        let signature = Signature::now("Test User", "test@example.com").unwrap();
+        let tree = repo.find_tree(repo.index().unwrap().write_tree().unwrap()).unwrap();
+        let create = |message: &str, parents: &[&Commit]| {
+            repo.find_commit(repo.commit(None, &signature, &signature, message, &tree, parents).unwrap()).unwrap()
+        };
+
+        let first = create("🎉 begin\n", &[]);
+        update_head(&repo, first.id(), None, "commit (initial): begin").unwrap();
+        assert_eq!(repo.head().unwrap().target(), Some(first.id()));
+        // the branch exists now, so it is not created again
+        assert!(update_head(&repo, first.id(), None, "commit (initial): begin").is_err());
+
+        let second = create("✨ add\n", &[&first]);
+        let concurrent = create("🐛 fix\n", &[&first]);
+        update_head(&repo, concurrent.id(), Some(first.id()), "commit: fix").unwrap();
+
+        // HEAD moved since `first` was read: the concurrent commit is kept
+        let error = update_head(&repo, second.id(), Some(first.id()), "commit: add").unwrap_err();
+        assert!(error.message().contains("changed while committing"));
+        assert_eq!(repo.head().unwrap().target(), Some(concurrent.id()));
+
+        repo.set_head_detached(concurrent.id()).unwrap();
+        update_head(&repo, second.id(), Some(concurrent.id()), "commit: add").unwrap();
+        assert_eq!(repo.head().unwrap().target(), Some(second.id()));
+        assert!(update_head(&repo, first.id(), Some(concurrent.id()), "commit: add").is_err());
+    }
+
+    #[test]
+    fn test_commit_allowed_scopes() {
+        let temp_dir = tempdir().unwrap();
+        let repo = Repository::init(temp_dir.path()).unwrap();
+
+        let mut config = repo.config().unwrap();
+        config.set_str("user.name", "Test User").unwrap();
+        config.set_str("user.email", "test@example.com").unwrap();
+        std::fs::write(
+            temp_dir.path().join(".glyphit.toml"),
+            "template = \"conventional\"\nscopes = [\"cli\", \"parser\"]\n",
+        ).unwrap();
+
+        let args = CommitArgs { scope: Some("docs".to_string()), ..non_interactive("memo", "guide") };
+        assert!(commit(Some(&repo), &args).is_err());
+
+        let args = CommitArgs { scope: Some("cli".to_string()), ..non_interactive("memo", "guide") };
+        let oid = commit(Some(&repo), &args).unwrap();
+        assert_eq!(repo.find_commit(oid).unwrap().message().unwrap(), "📝 docs(cli): guide\n");
+    }
+
+    #[test]
+    fn test_commit_repository_config() {
+        let temp_dir = tempdir().unwrap();
+        let repo = Repository::init(temp_dir.path()).unwrap();
+
+        let mut config = repo.config().unwrap();
+        config.set_str("user.name", "Test User").unwrap();
+        config.set_str("user.email", "test@example.com").unwrap();
+        std::fs::write(
+            temp_dir.path().join(".glyphit.toml"),
+            "allowed_emojis = [\"bug\"]\nsubject_max_length = 12\ninteractive = false\n",
+        ).unwrap();
+
+        // the emoji is not allowed
+        assert!(commit(Some(&repo), &non_interactive("sparkles", "short")).is_err());
+        // the subject is too long
+        assert!(commit(Some(&repo), &non_interactive("bug", "a rather long subject")).is_err());
+        // the config disables prompts, so a missing title is an error
+        let args = CommitArgs { emoji: Some("bug".to_string()), ..Default::default() };
+        assert!(commit(Some(&repo), &args).is_err());
+
+        assert!(commit(Some(&repo), &non_interactive("bug", "short")).is_ok());
+    }
+
+    #[test]
+    fn test_unknown_emoji_message() {
+        let catalog = EmojiCatalog::builtin();
+
+        assert_eq!(
+            unknown_emoji_message(&catalog, "deprecate"),
+            "unknown emoji 'deprecate', did you mean: 🗑️ :wastebasket:"
+        );
+        assert_eq!(unknown_emoji_message(&catalog, "zzzz"), "unknown emoji 'zzzz'");
+    }
+
+    #[test]
+    fn test_commit_non_interactive_missing_title() {
+        let temp_dir = tempdir().unwrap();
+        let repo = Repository::init(temp_dir.path()).unwrap();
+
+        let args = CommitArgs {
+            emoji: Some("sparkles".to_string()),
+            no_interactive: true,
+            ..Default::default()
+        };
+
+        assert!(commit(Some(&repo), &args).is_err());
+        assert!(repo.head().is_err());
+    }
+
+    #[test]
+    fn test_commit_unknown_emoji() {
+        let temp_dir = tempdir().unwrap();
+        let repo = Repository::init(temp_dir.path()).unwrap();
+
+        assert!(commit(Some(&repo), &non_interactive(":not_an_emoji:", "title")).is_err());
+    }
+
+    #[cfg(unix)]
+    fn write_hook(dir: &std::path::Path, name: &str, script: &str) {
+        use std::os::unix::fs::PermissionsExt;
+
+        std::fs::create_dir_all(dir).unwrap();
+        std::fs::write(dir.join(name), script).unwrap();
+        std::fs::set_permissions(dir.join(name), std::fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_commit_runs_hooks() {
+        let temp_dir = tempdir().unwrap();
+        let repo = Repository::init(temp_dir.path()).unwrap();
+
+        let mut config = repo.config().unwrap();
+        config.set_str("user.name", "Test User").unwrap();
+        config.set_str("user.email", "test@example.com").unwrap();
+
+        // pre-commit writes a file, the message hooks edit the message
+        let hooks = repo.path().join("hooks");
+        write_hook(&hooks, "pre-commit", "#!/bin/sh\necho pre >> hooks.log\n");
+        write_hook(&hooks, "prepare-commit-msg", "#!/bin/sh\necho \"prepare $2\" >> hooks.log\n");
+        write_hook(&hooks, "commit-msg", "#!/bin/sh\nprintf '\\nReviewed-by: hook\\n' >> \"$1\"\necho msg >> hooks.log\n");
+        write_hook(&hooks, "post-commit", "#!/bin/sh\necho post >> hooks.log\nexit 1\n");
+
+        let oid = commit(Some(&repo), &non_interactive("bug", "hooked")).unwrap();
+
+        assert_eq!(repo.find_commit(oid).unwrap().message().unwrap(), "🐛 hooked\n\nReviewed-by: hook\n");
+        assert_eq!(
+            std::fs::read_to_string(temp_dir.path().join("hooks.log")).unwrap(),
+            "pre\nprepare message\nmsg\npost\n"
+        );
+
+        // --no-verify skips pre-commit and commit-msg only
+        std::fs::remove_file(temp_dir.path().join("hooks.log")).unwrap();
+        let args = CommitArgs { no_verify: true, ..non_interactive("bug", "unverified") };
+        let oid = commit(Some(&repo), &args).unwrap();
+
+        assert_eq!(repo.find_commit(oid).unwrap().message().unwrap(), "🐛 unverified\n");
+        assert_eq!(
+            std::fs::read_to_string(temp_dir.path().join("hooks.log")).unwrap(),
+            "prepare message\npost\n"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_commit_aborts_on_failing_hooks() {
+        let temp_dir = tempdir().unwrap();
+        let repo = Repository::init(temp_dir.path()).unwrap();
+
+        let mut config = repo.config().unwrap();
+        config.set_str("user.name", "Test User").unwrap();
+        config.set_str("user.email", "test@example.com").unwrap();
+        config.set_str("core.hooksPath", "githooks").unwrap();
+
+        let hooks = temp_dir.path().join("githooks");
+        write_hook(&hooks, "pre-commit", "#!/bin/sh\necho secret found >&2\nexit 1\n");
+
+        assert!(commit(Some(&repo), &non_interactive("bug", "rejected")).is_err());
+        assert!(repo.head().is_err());
+
+        write_hook(&hooks, "pre-commit", "#!/bin/sh\nexit 0\n");
+        write_hook(&hooks, "commit-msg", "#!/bin/sh\n: > \"$1\"\n");
+        assert!(commit(Some(&repo), &non_interactive("bug", "emptied")).is_err());
+        assert!(repo.head().is_err());
+
+        let args = CommitArgs { no_verify: true, ..non_interactive("bug", "skipped") };
+        assert!(commit(Some(&repo), &args).is_ok());
+    }
+}
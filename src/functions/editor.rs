@@ -0,0 +1,177 @@
+use std::path::Path;
+use std::process::Command;
+use git2::{Delta, Error, ErrorClass, ErrorCode, Repository};
+use crate::types::staged::StagedChange;
+
+// used when no editor is configured at all, like git
+const DEFAULT_EDITOR: &str = "vi";
+
+/// Returns the editor command git would use: `$GIT_EDITOR`, `core.editor`,
+/// `$VISUAL`, `$EDITOR`, then `vi`.
+///
+/// # Errors
+///
+/// Returns an `Err(Error)` if the repository configuration cannot be read.
+pub fn editor_command(repo: &Repository) -> Result<String, Error> {
+    let from_env = |name: &str| std::env::var(name).ok();
+    Ok(choose_editor([
+        from_env("GIT_EDITOR"),
+        repo.config()?.get_string("core.editor").ok(),
+        from_env("VISUAL"),
+        from_env("EDITOR"),
+    ]))
+}
+
+// the first non-blank candidate, in order of precedence
+fn choose_editor(candidates: [Option<String>; 4]) -> String {
+    candidates.into_iter()
+        .flatten()
+        .find(|editor| !editor.trim().is_empty())
+        .unwrap_or_else(|| DEFAULT_EDITOR.to_string())
+}
+
+/// Opens a file in the configured editor and waits for it to exit.
+///
+/// The editor command goes through the shell, so it may carry arguments
+/// (`code --wait`). The `:` editor, which git uses to mean "no editor", is
+/// not run.
+///
+/// # Errors
+///
+/// Returns an `Err(Error)` if the editor cannot be started or exits with a
+/// non-zero status.
+pub fn edit_file(repo: &Repository, path: &Path) -> Result<(), Error> {
+    run_editor(&editor_command(repo)?, repo.workdir().unwrap_or_else(|| repo.path()), path)
+}
+
+fn run_editor(editor: &str, dir: &Path, path: &Path) -> Result<(), Error> {
+    if editor.trim() == ":" {
+        return Ok(());
+    }
+
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg(format!("{} \"$@\"", editor))
+        .arg(editor)
+        .arg(path)
+        .current_dir(dir)
+        .status()
+        .map_err(|e| Error::from_str(format!("cannot run the editor '{}': {}", editor, e).as_str()))?;
+
+    if !status.success() {
+        return Err(Error::new(ErrorCode::User, ErrorClass::Callback, format!(
+            "the editor '{}' failed ({}), aborting commit", editor, status
+        )));
+    }
+    Ok(())
+}
+
+/// Builds the content of the message file opened in the editor: the composed
+/// message followed by commented-out help and the list of staged files.
+pub fn edit_template(message: &str, changes: &[StagedChange], comment_char: char) -> String {
+    let c = comment_char;
+    let mut content = message.to_string();
+    content.push('\n');
+    content.push_str(&format!(
+        "{c} Please enter the commit message for your changes. Lines starting\n\
+         {c} with '{c}' will be ignored, and an empty message aborts the commit.\n"
+    ));
+
+    if !changes.is_empty() {
+        content.push_str(&format!("{c}\n{c} Changes to be committed:\n"));
+        for change in changes {
+            let status = match change.status {
+                Delta::Added => "new file",
+                Delta::Deleted => "deleted",
+                Delta::Renamed => "renamed",
+                Delta::Copied => "copied",
+                Delta::Typechange => "typechange",
+                _ => "modified",
+            };
+            let path = match &change.old_path {
+                Some(old_path) => format!("{} -> {}", old_path, change.path),
+                None => change.path.clone(),
+            };
+            content.push_str(&format!("{c}\t{:<12}{}\n", format!("{}:", status), path));
+        }
+    }
+    content
+}
+
+/// Cleans up a commit message like git does.
+///
+/// Trailing spaces and surrounding blank lines are removed, and consecutive
+/// blank lines are collapsed. With a `comment_char`, comment lines are
+/// removed too (git's `strip` cleanup, used when the message was edited).
+pub fn clean_message(message: &str, comment_char: Option<char>) -> String {
+    let mut lines: Vec<&str> = Vec::new();
+    for line in message.lines().map(str::trim_end) {
+        if comment_char.is_some_and(|c| line.starts_with(c)) {
+            continue;
+        }
+        if line.is_empty() && lines.last().is_none_or(|last| last.is_empty()) {
+            continue;
+        }
+        lines.push(line);
+    }
+    while lines.last().is_some_and(|last| last.is_empty()) {
+        lines.pop();
+    }
+    if lines.is_empty() {
+        return String::new();
+    }
+    format!("{}\n", lines.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_clean_message() {
+        let message = "\n\nsubject  \n\n\n# a comment\nbody\n\n";
+
+        assert_eq!(clean_message(message, None), "subject\n\n# a comment\nbody\n");
+        assert_eq!(clean_message(message, Some('#')), "subject\n\nbody\n");
+        assert_eq!(clean_message("# only comments\n\n", Some('#')), "");
+    }
+
+    #[test]
+    fn test_edit_template() {
+        let changes = vec![
+            StagedChange { path: "src/main.rs".to_string(), old_path: None, status: Delta::Modified },
+            StagedChange { path: "src/new.rs".to_string(), old_path: Some("src/old.rs".to_string()), status: Delta::Renamed },
+        ];
+
+        let content = edit_template("✨ subject\n", &changes, ';');
+
+        assert!(content.starts_with("✨ subject\n\n; Please enter"));
+        assert!(content.contains(";\tmodified:   src/main.rs\n"));
+        assert!(content.contains(";\trenamed:    src/old.rs -> src/new.rs\n"));
+        assert_eq!(clean_message(&content, Some(';')), "✨ subject\n");
+    }
+
+    #[test]
+    fn test_choose_editor() {
+        let some = |editor: &str| Some(editor.to_string());
+
+        assert_eq!(choose_editor([some("git-editor"), some("core"), some("visual"), some("editor")]), "git-editor");
+        assert_eq!(choose_editor([None, some("code --wait"), some("visual"), None]), "code --wait");
+        assert_eq!(choose_editor([some(" "), None, None, some("nano")]), "nano");
+        assert_eq!(choose_editor([None, None, None, None]), "vi");
+    }
+
+    #[test]
+    fn test_run_editor() {
+        let temp_dir = tempdir().unwrap();
+        let file = temp_dir.path().join("MESSAGE");
+        std::fs::write(&file, "draft\n").unwrap();
+
+        run_editor("sed -i -e 's/draft/final/'", temp_dir.path(), &file).unwrap();
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "final\n");
+
+        run_editor(":", temp_dir.path(), &file).unwrap();
+        assert!(run_editor("false", temp_dir.path(), &file).is_err());
+    }
+}
@@ -0,0 +1,244 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use git2::{Error, Repository, Sort};
+use serde::{Deserialize, Serialize};
+use crate::types::config::HistoryMode;
+use crate::types::emoji::EmojiCatalog;
+
+// how many of the user's recent commits are scanned in `commits` mode
+const SCANNED_COMMITS: usize = 200;
+
+// how many frequently used emojis float to the top of the picker
+const TOP_EMOJIS: usize = 5;
+
+// a use loses half of its weight after this many seconds (two weeks)
+const HALF_LIFE: f64 = 14.0 * 24.0 * 3600.0;
+
+/// How often, and how recently, an emoji has been used.
+///
+/// # Fields
+///
+/// * `count` - The number of commits using the emoji.
+/// * `last_used` - The time of the most recent one, in seconds since the
+///   Unix epoch.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Usage {
+    pub count: u64,
+    pub last_used: i64,
+}
+
+/// The per-user emoji usage history, keyed by shortcode.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq)]
+pub struct UsageHistory {
+    #[serde(default)]
+    pub emojis: BTreeMap<String, Usage>,
+}
+
+/// Returns the path of the usage state file,
+/// `$XDG_STATE_HOME/glyphit/history.toml` (or
+/// `~/.local/state/glyphit/history.toml`).
+pub fn state_file_path() -> Option<PathBuf> {
+    let base = match std::env::var_os("XDG_STATE_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => match (std::env::var_os("HOME"), std::env::var_os("LOCALAPPDATA")) {
+            (Some(home), _) => PathBuf::from(home).join(".local").join("state"),
+            (None, Some(local)) => PathBuf::from(local),
+            (None, None) => return None,
+        },
+    };
+    Some(base.join("glyphit").join("history.toml"))
+}
+
+fn now() -> i64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs() as i64).unwrap_or_default()
+}
+
+impl UsageHistory {
+    /// Reads the history from a state file; a missing file is an empty history.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `Err(Error)` if the file exists but is not a valid history.
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        match std::fs::read_to_string(path) {
+            Ok(content) => toml::from_str(&content)
+                .map_err(|e| Error::from_str(format!("invalid history file {}: {}", path.display(), e.message()).as_str())),
+            Err(_) => Ok(Self::default()),
+        }
+    }
+
+    /// Writes the history to a state file, creating its directory if needed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `Err(Error)` if the file cannot be written.
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        let content = toml::to_string(self)
+            .map_err(|e| Error::from_str(format!("cannot serialize history: {}", e).as_str()))?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| Error::from_str(format!("cannot create {}: {}", parent.display(), e).as_str()))?;
+        }
+        std::fs::write(path, content)
+            .map_err(|e| Error::from_str(format!("cannot write {}: {}", path.display(), e).as_str()))
+    }
+
+    /// Records one use of an emoji at the given time.
+    pub fn record(&mut self, shortcode: &str, time: i64) {
+        let usage = self.emojis.entry(shortcode.to_string()).or_default();
+        usage.count += 1;
+        usage.last_used = usage.last_used.max(time);
+    }
+
+    /// Builds a history from the recent commits authored by `email`, reading
+    /// the leading emoji of every message.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `Err(Error)` if the history of `HEAD` cannot be walked.
+    pub fn from_commits(repo: &Repository, email: &str, catalog: &EmojiCatalog) -> Result<Self, Error> {
+        let mut history = Self::default();
+        if repo.head().is_err() {
+            return Ok(history);
+        }
+
+        let mut revwalk = repo.revwalk()?;
+        revwalk.set_sorting(Sort::TIME)?;
+        revwalk.push_head()?;
+
+        for oid in revwalk.take(SCANNED_COMMITS) {
+            let commit = repo.find_commit(oid?)?;
+            if commit.author().email() != Some(email) {
+                continue;
+            }
+            if let Some((emoji, _)) = catalog.match_prefix(commit.message().unwrap_or_default()) {
+                history.record(&emoji.shortcode, commit.time().seconds());
+            }
+        }
+        Ok(history)
+    }
+
+    /// Returns the most used shortcodes, best first.
+    ///
+    /// Every emoji is weighted by its use count, halved for every two weeks
+    /// since its last use, so that both frequent and recent emojis rank high.
+    pub fn top(&self, now: i64, limit: usize) -> Vec<String> {
+        let mut ranked: Vec<(&String, f64)> = self.emojis.iter()
+            .map(|(shortcode, usage)| {
+                let age = (now - usage.last_used).max(0) as f64;
+                (shortcode, usage.count as f64 * 0.5f64.powf(age / HALF_LIFE))
+            })
+            .collect();
+        ranked.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        ranked.into_iter().take(limit).map(|(shortcode, _)| shortcode.clone()).collect()
+    }
+}
+
+/// Returns the emojis the current user uses most, according to the
+/// configured history mode.
+///
+/// # Errors
+///
+/// Returns an `Err(Error)` if the history cannot be read.
+pub fn frequent_emojis(repo: &Repository, mode: HistoryMode, catalog: &EmojiCatalog) -> Result<Vec<String>, Error> {
+    let history = match mode {
+        HistoryMode::Off => return Ok(Vec::new()),
+        HistoryMode::State => match state_file_path() {
+            Some(path) => UsageHistory::load(&path)?,
+            None => return Ok(Vec::new()),
+        },
+        HistoryMode::Commits => match repo.config()?.get_string("user.email") {
+            Ok(email) => UsageHistory::from_commits(repo, &email, catalog)?,
+            Err(_) => return Ok(Vec::new()),
+        },
+    };
+    Ok(history.top(now(), TOP_EMOJIS))
+}
+
+/// Records the use of an emoji in the state file, when the history mode
+/// keeps one.
+///
+/// # Errors
+///
+/// Returns an `Err(Error)` if the state file cannot be read or written.
+pub fn record_usage(mode: HistoryMode, shortcode: &str) -> Result<(), Error> {
+    if mode != HistoryMode::State {
+        return Ok(());
+    }
+    let path = match state_file_path() {
+        Some(path) => path,
+        None => return Ok(()),
+    };
+    let mut history = UsageHistory::load(&path)?;
+    history.record(shortcode, now());
+    history.save(&path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use git2::Signature;
+    use tempfile::tempdir;
+
+    const DAY: i64 = 24 * 3600;
+
+    #[test]
+    fn test_top_favours_frequent_and_recent() {
+        let now = 1_000 * DAY;
+        let mut history = UsageHistory::default();
+        for _ in 0..10 {
+            history.record("bug", now - 90 * DAY);
+        }
+        for _ in 0..4 {
+            history.record("sparkles", now - DAY);
+        }
+        history.record("memo", now);
+        history.record("recycle", now - 400 * DAY);
+
+        assert_eq!(history.top(now, 3), vec!["sparkles", "memo", "bug"]);
+        assert_eq!(history.top(now, 10).len(), 4);
+    }
+
+    #[test]
+    fn test_state_file_round_trip() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("state").join("history.toml");
+
+        assert_eq!(UsageHistory::load(&path).unwrap(), UsageHistory::default());
+
+        let mut history = UsageHistory::default();
+        history.record("bug", 10);
+        history.record("bug", 5);
+        history.save(&path).unwrap();
+
+        let loaded = UsageHistory::load(&path).unwrap();
+        assert_eq!(loaded.emojis["bug"], Usage { count: 2, last_used: 10 });
+    }
+
+    #[test]
+    fn test_from_commits() {
+        let temp_dir = tempdir().unwrap();
+        let repo = Repository::init(temp_dir.path()).unwrap();
+        let tree = repo.find_tree(repo.index().unwrap().write_tree().unwrap()).unwrap();
+
+        let mut parents: Vec<git2::Commit> = Vec::new();
+        for (email, message) in [
+            ("me@example.com", "🐛 fix"),
+            ("me@example.com", ":bug: fix again"),
+            ("other@example.com", "✨ not mine"),
+            ("me@example.com", "♻️ refactor"),
+            ("me@example.com", "no emoji"),
+        ] {
+            let signature = Signature::now("Someone", email).unwrap();
+            let parent_refs: Vec<&git2::Commit> = parents.iter().collect();
+            let oid = repo.commit(Some("HEAD"), &signature, &signature, message, &tree, &parent_refs).unwrap();
+            parents = vec![repo.find_commit(oid).unwrap()];
+        }
+
+        let history = UsageHistory::from_commits(&repo, "me@example.com", &EmojiCatalog::builtin()).unwrap();
+
+        assert_eq!(history.emojis.keys().collect::<Vec<&String>>(), vec!["bug", "recycle"]);
+        assert_eq!(history.emojis["bug"].count, 2);
+    }
+}
@@ -0,0 +1,507 @@
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use git2::{Error, ErrorClass, ErrorCode, Repository};
+use crate::functions::commit::prompt_emoji;
+use crate::functions::suggest::suggest_for_repository;
+use crate::types::commands::HooksAction;
+use crate::types::config::Config;
+use crate::types::emoji::EmojiCatalog;
+use crate::types::repository::{comment_char, get_current_repository};
+use crate::types::template::strip_autosquash;
+
+/// Name of the hook validating commit messages with `glyphit lint`.
+pub const COMMIT_MSG: &str = "commit-msg";
+
+/// Name of the hook adding an emoji to messages written with plain `git commit`.
+pub const PREPARE_COMMIT_MSG: &str = "prepare-commit-msg";
+
+// marks the hooks written by glyphit, so that they are never mistaken for
+// foreign hooks
+const MARKER: &str = "# installed by glyphit";
+
+// suffix of a foreign hook moved aside by `install --force`, run before ours
+const CHAINED_SUFFIX: &str = ".glyphit-chained";
+
+// sources of prepare-commit-msg for which git already wrote a complete message
+const PREPARED_SOURCES: [&str; 3] = ["merge", "squash", "commit"];
+
+fn hook_script(name: &str, command: &str) -> String {
+    format!(
+        "#!/bin/sh\n\
+         {marker}: run `glyphit hooks uninstall` to remove it\n\
+         chained=\"$(dirname \"$0\")/{name}{suffix}\"\n\
+         if [ -x \"$chained\" ]; then\n\
+         \x20   \"$chained\" \"$@\" || exit $?\n\
+         fi\n\
+         {command}\n",
+        marker = MARKER, name = name, suffix = CHAINED_SUFFIX, command = command,
+    )
+}
+
+fn commit_msg_script() -> String {
+    hook_script(COMMIT_MSG, "exec glyphit lint \"$1\"")
+}
+
+// git runs hooks without a terminal on stdin; reattach it so the picker works
+fn prepare_commit_msg_script() -> String {
+    hook_script(
+        PREPARE_COMMIT_MSG,
+        "if [ -t 2 ] && (: < /dev/tty) 2>/dev/null; then\n    exec < /dev/tty\nfi\nexec glyphit hooks prepare-commit-msg \"$@\"",
+    )
+}
+
+/// The state of a hook in the hooks directory.
+///
+/// # Possible values
+///
+/// * `Missing` - There is no hook.
+/// * `Installed` - The hook was installed by glyphit; `chained` tells whether
+///   it runs a previous hook first.
+/// * `Foreign` - Another hook is installed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookState {
+    Missing,
+    Installed { chained: bool },
+    Foreign,
+}
+
+/// Returns the directory git runs hooks from: `core.hooksPath` (relative to
+/// the working tree) when set, `.git/hooks` otherwise.
+///
+/// # Errors
+///
+/// Returns an `Err(Error)` if the repository configuration cannot be read.
+pub fn hooks_dir(repo: &Repository) -> Result<PathBuf, Error> {
+    match repo.config()?.get_path("core.hooksPath") {
+        Ok(path) if path.is_absolute() => Ok(path),
+        Ok(path) => Ok(repo.workdir().unwrap_or_else(|| repo.path()).join(path)),
+        Err(_) => Ok(repo.path().join("hooks")),
+    }
+}
+
+fn chained_path(dir: &Path, name: &str) -> PathBuf {
+    dir.join(format!("{}{}", name, CHAINED_SUFFIX))
+}
+
+/// Returns the state of the named hook in a hooks directory.
+pub fn hook_state(dir: &Path, name: &str) -> HookState {
+    match std::fs::read_to_string(dir.join(name)) {
+        Ok(content) if content.contains(MARKER) => HookState::Installed { chained: chained_path(dir, name).exists() },
+        Ok(_) => HookState::Foreign,
+        Err(_) if dir.join(name).exists() => HookState::Foreign,
+        Err(_) => HookState::Missing,
+    }
+}
+
+fn io_error(action: &str, path: &Path, e: std::io::Error) -> Error {
+    Error::from_str(format!("cannot {} {}: {}", action, path.display(), e).as_str())
+}
+
+fn foreign_hook_error(path: &Path) -> Error {
+    Error::new(ErrorCode::Exists, ErrorClass::Invalid, format!(
+        "{} already exists and was not installed by glyphit; use --force to run it before the glyphit hook",
+        path.display()
+    ))
+}
+
+fn write_hook(dir: &Path, name: &str, script: &str, force: bool) -> Result<(), Error> {
+    let path = dir.join(name);
+    match hook_state(dir, name) {
+        HookState::Foreign if !force => return Err(foreign_hook_error(&path)),
+        HookState::Foreign => {
+            let chained = chained_path(dir, name);
+            if chained.exists() {
+                return Err(Error::new(ErrorCode::Exists, ErrorClass::Invalid, format!(
+                    "cannot chain {}: {} already exists", path.display(), chained.display()
+                )));
+            }
+            std::fs::rename(&path, &chained).map_err(|e| io_error("move", &path, e))?;
+        }
+        HookState::Missing | HookState::Installed { .. } => {}
+    }
+
+    std::fs::write(&path, script).map_err(|e| io_error("write", &path, e))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755))
+            .map_err(|e| io_error("make executable", &path, e))?;
+    }
+    Ok(())
+}
+
+// git only runs hooks that are executable
+fn is_executable(path: &Path) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        path.metadata().is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+    }
+    #[cfg(not(unix))]
+    {
+        path.is_file()
+    }
+}
+
+/// Runs a repository hook the way git does, if it exists and is executable.
+///
+/// The hook runs from the root of the working tree, with `args`,
+/// `GIT_INDEX_FILE` pointing at the repository index plus the given `env`
+/// variables, no standard input and its output sent to the standard error.
+/// See [`run_hook_with_input`] for hooks that read their standard input.
+///
+/// # Errors
+///
+/// Returns an `Err(Error)` if the hooks directory cannot be resolved, the
+/// hook cannot be started or it exits with a non-zero status.
+///
+/// # Returns
+///
+/// * `Ok(true)` if the hook ran and succeeded, `Ok(false)` if there is none.
+pub fn run_hook(repo: &Repository, name: &str, args: &[&str], env: &[(&str, &str)]) -> Result<bool, Error> {
+    run_hook_with_input(repo, name, args, env, None)
+}
+
+/// Runs a repository hook like [`run_hook`], writing `input` to its
+/// standard input.
+///
+/// # Errors
+///
+/// Returns an `Err(Error)` if the hooks directory cannot be resolved, the
+/// hook cannot be started or it exits with a non-zero status.
+///
+/// # Returns
+///
+/// * `Ok(true)` if the hook ran and succeeded, `Ok(false)` if there is none.
+pub fn run_hook_with_input(
+    repo: &Repository,
+    name: &str,
+    args: &[&str],
+    env: &[(&str, &str)],
+    input: Option<&str>,
+) -> Result<bool, Error> {
+    let path = hooks_dir(repo)?.join(name);
+    if !is_executable(&path) {
+        return Ok(false);
+    }
+
+    let index_path = repo.path().join("index");
+    let mut child = Command::new(&path)
+        .args(args)
+        .current_dir(repo.workdir().unwrap_or_else(|| repo.path()))
+        .env("GIT_INDEX_FILE", &index_path)
+        .envs(env.iter().copied())
+        .stdin(if input.is_some() { Stdio::piped() } else { Stdio::null() })
+        .stdout(Stdio::from(std::io::stderr()))
+        .spawn()
+        .map_err(|e| io_error("run", &path, e))?;
+
+    // a hook may exit without reading its input: a broken pipe is not an error
+    if let (Some(input), Some(mut stdin)) = (input, child.stdin.take()) {
+        let _ = stdin.write_all(input.as_bytes());
+    }
+    let status = child.wait().map_err(|e| io_error("run", &path, e))?;
+
+    if !status.success() {
+        return Err(Error::new(ErrorCode::User, ErrorClass::Callback, format!(
+            "the {} hook failed ({})", name, status
+        )));
+    }
+    Ok(true)
+}
+
+/// Installs the glyphit hooks in the repository hooks directory.
+///
+/// The `commit-msg` hook is always installed; the `prepare-commit-msg` hook
+/// only when `prepare` is set. Hooks installed by glyphit are updated in
+/// place. A foreign hook is an error unless `force` is set, in which case it
+/// is renamed to `<hook>.glyphit-chained` and run before the glyphit hook.
+///
+/// # Errors
+///
+/// Returns an `Err(Error)` if a foreign hook is in the way or a hook cannot
+/// be written.
+pub fn install(repo: &Repository, prepare: bool, force: bool) -> Result<Vec<PathBuf>, Error> {
+    let dir = hooks_dir(repo)?;
+    std::fs::create_dir_all(&dir).map_err(|e| io_error("create", &dir, e))?;
+
+    let mut hooks = vec![(COMMIT_MSG, commit_msg_script())];
+    if prepare {
+        hooks.push((PREPARE_COMMIT_MSG, prepare_commit_msg_script()));
+    }
+
+    // check every hook first, so that nothing is written when one is refused
+    if !force
+        && let Some((name, _)) = hooks.iter().find(|(name, _)| hook_state(&dir, name) == HookState::Foreign) {
+        return Err(foreign_hook_error(&dir.join(name)));
+    }
+
+    let mut installed = Vec::new();
+    for (name, script) in hooks {
+        write_hook(&dir, name, &script, force)?;
+        installed.push(dir.join(name));
+    }
+    Ok(installed)
+}
+
+/// Removes the hooks installed by glyphit, restoring the hooks they chained to.
+/// Foreign hooks are left untouched.
+///
+/// # Errors
+///
+/// Returns an `Err(Error)` if a hook cannot be removed or restored.
+pub fn uninstall(repo: &Repository) -> Result<Vec<PathBuf>, Error> {
+    let dir = hooks_dir(repo)?;
+    let mut removed = Vec::new();
+    for name in [COMMIT_MSG, PREPARE_COMMIT_MSG] {
+        if let HookState::Installed { chained } = hook_state(&dir, name) {
+            let path = dir.join(name);
+            std::fs::remove_file(&path).map_err(|e| io_error("remove", &path, e))?;
+            if chained {
+                std::fs::rename(chained_path(&dir, name), &path).map_err(|e| io_error("restore", &path, e))?;
+            }
+            removed.push(path);
+        }
+    }
+    Ok(removed)
+}
+
+// whether a line holds a known emoji (glyph or shortcode) anywhere
+fn contains_emoji(catalog: &EmojiCatalog, line: &str) -> bool {
+    line.char_indices().any(|(index, _)| catalog.match_prefix(&line[index..]).is_some())
+}
+
+/// Adds an emoji to the message file of a plain `git commit`, as the
+/// `prepare-commit-msg` hook.
+///
+/// Nothing is done for merges, squashes, amended commits, or messages that
+/// already start with a known emoji. A message given with `-m` (or composed
+/// by `glyphit commit`) is also left alone when its subject has a known emoji
+/// anywhere, as custom templates like `{subject} {emoji}` put it. Otherwise the emoji is picked with the
+/// picker when a terminal is available and prompts are enabled, or is the
+/// first emoji suggested by the staged changes.
+///
+/// # Errors
+///
+/// Returns an `Err(Error)` if the configuration or the message file cannot
+/// be read or written, or the picker fails.
+pub fn prepare_commit_msg(repo: &Repository, file: &Path, source: Option<&str>) -> Result<(), Error> {
+    if source.is_some_and(|source| PREPARED_SOURCES.contains(&source)) {
+        return Ok(());
+    }
+
+    let config = Config::load(repo)?;
+    let catalog = EmojiCatalog::load(&config)?;
+    let message = std::fs::read_to_string(file).map_err(|e| io_error("read", file, e))?;
+    let comment_char = comment_char(repo)?;
+
+    let first_line = message.lines().next().unwrap_or_default();
+    if catalog.match_prefix(strip_autosquash(first_line)).is_some() {
+        return Ok(());
+    }
+    if source == Some("message") && contains_emoji(&catalog, first_line) {
+        return Ok(());
+    }
+
+    let emoji = if config.interactive && std::io::stdin().is_terminal() {
+        prompt_emoji(repo, &config, &catalog, &[])?
+    } else {
+        let suggested = suggest_for_repository(repo, &config.suggestions)?.into_iter()
+            .find_map(|shortcode| catalog.find(&shortcode).cloned());
+        match suggested {
+            Some(emoji) => emoji,
+            None => return Ok(()),
+        }
+    };
+
+    let prefix = emoji.render(config.emoji_format);
+    let prepared = if first_line.starts_with(comment_char) {
+        format!("{} \n{}", prefix, message)
+    } else {
+        format!("{} {}", prefix, message)
+    };
+    std::fs::write(file, prepared).map_err(|e| io_error("write", file, e))
+}
+
+/// Installs, removes or reports the glyphit git hooks.
+///
+/// # Arguments
+///
+/// * `repo` - An optional reference to a `Repository`. If `None`, the function
+///   attempts to find the current repository automatically.
+/// * `action` - What to do: `install`, `uninstall`, `status`, or run the
+///   `prepare-commit-msg` hook.
+///
+/// # Errors
+///
+/// Returns an `Err(Error)` if:
+/// - The current repository cannot be determined.
+/// - The hooks directory cannot be resolved.
+/// - A foreign hook is in the way of `install` without `--force`.
+/// - A hook or message file cannot be read or written.
+pub fn hooks(repo: Option<&Repository>, action: &HooksAction) -> Result<(), Error> {
+    let owned_repo;
+    let current_repo = match repo {
+        Some(r) => r,
+        _ => {
+            owned_repo = get_current_repository()?;
+            &owned_repo
+        }
+    };
+
+    match action {
+        HooksAction::Install { prepare_commit_msg, force } => {
+            for path in install(current_repo, *prepare_commit_msg, *force)? {
+                println!("installed {}", path.display());
+            }
+        }
+        HooksAction::Uninstall => {
+            let removed = uninstall(current_repo)?;
+            if removed.is_empty() {
+                println!("no glyphit hook installed");
+            }
+            for path in removed {
+                println!("removed {}", path.display());
+            }
+        }
+        HooksAction::Status => {
+            let dir = hooks_dir(current_repo)?;
+            println!("hooks directory: {}", dir.display());
+            for name in [COMMIT_MSG, PREPARE_COMMIT_MSG] {
+                let state = match hook_state(&dir, name) {
+                    HookState::Missing => "not installed",
+                    HookState::Installed { chained: false } => "installed",
+                    HookState::Installed { chained: true } => "installed, chained to a previous hook",
+                    HookState::Foreign => "foreign hook",
+                };
+                println!("{:<20} {}", name, state);
+            }
+        }
+        HooksAction::PrepareCommitMsg { file, source, .. } => {
+            prepare_commit_msg(current_repo, Path::new(file), source.as_deref())?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+    use crate::functions::add::add;
+
+    #[test]
+    fn test_install_and_uninstall() {
+        let temp_dir = tempdir().unwrap();
+        let repo = Repository::init(temp_dir.path()).unwrap();
+        let dir = hooks_dir(&repo).unwrap();
+        assert_eq!(dir, repo.path().join("hooks"));
+
+        install(&repo, false, false).unwrap();
+        assert_eq!(hook_state(&dir, COMMIT_MSG), HookState::Installed { chained: false });
+        assert_eq!(hook_state(&dir, PREPARE_COMMIT_MSG), HookState::Missing);
+
+        // reinstalling updates our own hooks
+        install(&repo, true, false).unwrap();
+        assert_eq!(hook_state(&dir, PREPARE_COMMIT_MSG), HookState::Installed { chained: false });
+        assert!(std::fs::read_to_string(dir.join(COMMIT_MSG)).unwrap().contains("glyphit lint"));
+
+        assert_eq!(uninstall(&repo).unwrap().len(), 2);
+        assert_eq!(hook_state(&dir, COMMIT_MSG), HookState::Missing);
+        assert_eq!(hook_state(&dir, PREPARE_COMMIT_MSG), HookState::Missing);
+    }
+
+    #[test]
+    fn test_foreign_hooks_are_chained_with_force() {
+        let temp_dir = tempdir().unwrap();
+        let repo = Repository::init(temp_dir.path()).unwrap();
+        let dir = hooks_dir(&repo).unwrap();
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join(COMMIT_MSG), "#!/bin/sh\nexit 0\n").unwrap();
+
+        assert!(install(&repo, true, false).is_err());
+        assert_eq!(hook_state(&dir, COMMIT_MSG), HookState::Foreign);
+        assert_eq!(hook_state(&dir, PREPARE_COMMIT_MSG), HookState::Missing);
+
+        install(&repo, false, true).unwrap();
+        assert_eq!(hook_state(&dir, COMMIT_MSG), HookState::Installed { chained: true });
+        assert_eq!(std::fs::read_to_string(chained_path(&dir, COMMIT_MSG)).unwrap(), "#!/bin/sh\nexit 0\n");
+
+        uninstall(&repo).unwrap();
+        assert_eq!(std::fs::read_to_string(dir.join(COMMIT_MSG)).unwrap(), "#!/bin/sh\nexit 0\n");
+        assert!(!chained_path(&dir, COMMIT_MSG).exists());
+    }
+
+    #[test]
+    fn test_hooks_path_is_respected() {
+        let temp_dir = tempdir().unwrap();
+        let repo = Repository::init(temp_dir.path()).unwrap();
+        repo.config().unwrap().set_str("core.hooksPath", ".githooks").unwrap();
+
+        install(&repo, false, false).unwrap();
+
+        assert!(temp_dir.path().join(".githooks").join(COMMIT_MSG).exists());
+        assert!(!repo.path().join("hooks").join(COMMIT_MSG).exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_hook() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = tempdir().unwrap();
+        let repo = Repository::init(temp_dir.path()).unwrap();
+        let dir = hooks_dir(&repo).unwrap();
+        std::fs::create_dir_all(&dir).unwrap();
+
+        assert!(!run_hook(&repo, "pre-commit", &[], &[]).unwrap());
+
+        let hook = dir.join("pre-commit");
+        std::fs::write(&hook, "#!/bin/sh\necho \"$1 $GIT_INDEX_FILE $EXTRA\" > out.txt\n").unwrap();
+        assert!(!run_hook(&repo, "pre-commit", &[], &[]).unwrap());
+
+        std::fs::set_permissions(&hook, std::fs::Permissions::from_mode(0o755)).unwrap();
+        assert!(run_hook(&repo, "pre-commit", &["arg"], &[("EXTRA", "extra")]).unwrap());
+        assert_eq!(
+            std::fs::read_to_string(temp_dir.path().join("out.txt")).unwrap(),
+            format!("arg {} extra\n", repo.path().join("index").display())
+        );
+
+        std::fs::write(&hook, "#!/bin/sh\nexit 3\n").unwrap();
+        assert!(run_hook(&repo, "pre-commit", &[], &[]).is_err());
+    }
+
+    #[test]
+    fn test_prepare_commit_msg() {
+        let temp_dir = tempdir().unwrap();
+        let repo = Repository::init(temp_dir.path()).unwrap();
+        repo.config().unwrap().set_bool("glyphit.interactive", false).unwrap();
+        std::fs::write(temp_dir.path().join("README.md"), "# readme\n").unwrap();
+        add(&["README.md".to_string()], Some(&repo)).unwrap();
+        let file = temp_dir.path().join("COMMIT_EDITMSG");
+
+        std::fs::write(&file, "\n# Please enter the commit message\n").unwrap();
+        prepare_commit_msg(&repo, &file, None).unwrap();
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "📝 \n# Please enter the commit message\n");
+
+        std::fs::write(&file, "document the API\n").unwrap();
+        prepare_commit_msg(&repo, &file, Some("message")).unwrap();
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "📝 document the API\n");
+
+        std::fs::write(&file, "🐛 fix crash\n").unwrap();
+        prepare_commit_msg(&repo, &file, Some("message")).unwrap();
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "🐛 fix crash\n");
+
+        // a custom template may put the emoji after the subject
+        for message in ["document the API 📝\n", "[docs] document the API :memo:\n"] {
+            std::fs::write(&file, message).unwrap();
+            prepare_commit_msg(&repo, &file, Some("message")).unwrap();
+            assert_eq!(std::fs::read_to_string(&file).unwrap(), message);
+        }
+
+        std::fs::write(&file, "Merge branch 'main'\n").unwrap();
+        prepare_commit_msg(&repo, &file, Some("merge")).unwrap();
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "Merge branch 'main'\n");
+    }
+}
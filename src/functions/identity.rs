@@ -0,0 +1,368 @@
+use git2::{ConfigLevel, Error, ErrorClass, ErrorCode, Repository, Signature, Time};
+use inquire::{Confirm, Text};
+use regex::Regex;
+use crate::functions::trailers::{full_identity, known_authors, resolve_author, split_identity};
+
+// `@1700000000`, or git's raw `1700000000 +0200`
+const RAW_DATE_PATTERN: &str = r"^@?(\d+)(?:\s+([+-]\d{2}:?\d{2}))?$";
+// `2005-04-07T22:13:13+02:00`, `2005-04-07 22:13`, `2005-04-07`
+const ISO_DATE_PATTERN: &str = r"^(\d{4})-(\d{2})-(\d{2})(?:[T ](\d{2}):(\d{2})(?::(\d{2}))?)?\s*(Z|[+-]\d{2}:?\d{2})?$";
+// `Thu, 07 Apr 2005 22:13:13 +0200`
+const RFC2822_DATE_PATTERN: &str = r"^(?:[A-Za-z]{3},\s*)?(\d{1,2}) ([A-Za-z]{3}) (\d{4}) (\d{2}):(\d{2})(?::(\d{2}))?(?:\s*([+-]\d{4}))?$";
+
+const MONTHS: [&str; 12] = ["jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec"];
+
+// days from 1970-01-01 to a civil date of the proleptic Gregorian calendar
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = if year >= 0 { year } else { year - 399 } / 400;
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+// the number of days of a month, February of leap years included
+fn days_in_month(year: i64, month: i64) -> i64 {
+    let (next_year, next_month) = if month == 12 { (year + 1, 1) } else { (year, month + 1) };
+    days_from_civil(next_year, next_month, 1) - days_from_civil(year, month, 1)
+}
+
+// the civil date of a number of days from 1970-01-01
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719468;
+    let era = if days >= 0 { days } else { days - 146096 } / 146097;
+    let day_of_era = days - era * 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+// `Z`, `+0200` or `+02:00`, in minutes
+fn parse_offset(offset: &str) -> Option<i32> {
+    if offset == "Z" {
+        return Some(0);
+    }
+    let sign = if offset.starts_with('-') { -1 } else { 1 };
+    let digits: String = offset[1..].chars().filter(char::is_ascii_digit).collect();
+    let hours: i32 = digits.get(..2)?.parse().ok()?;
+    let minutes: i32 = digits.get(2..4)?.parse().ok()?;
+    Some(sign * (hours * 60 + minutes))
+}
+
+/// Formats a time like `git log --date=iso`: `2005-04-07 22:13:13 +0200`,
+/// in its own time zone.
+pub fn format_date(time: &Time) -> String {
+    let offset = time.offset_minutes() as i64;
+    let local = time.seconds() + offset * 60;
+    let (year, month, day) = civil_from_days(local.div_euclid(86400));
+    let seconds = local.rem_euclid(86400);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02} {}{:02}{:02}",
+        year, month, day, seconds / 3600, seconds / 60 % 60, seconds % 60,
+        if offset < 0 { '-' } else { '+' }, offset.abs() / 60, offset.abs() % 60
+    )
+}
+
+/// Parses a date given to `--date` or in `GIT_AUTHOR_DATE` /
+/// `GIT_COMMITTER_DATE`, in one of the formats git accepts: a Unix timestamp
+/// (`@1700000000`, or `1700000000 +0200`), ISO 8601
+/// (`2005-04-07T22:13:13+02:00`) or RFC 2822
+/// (`Thu, 07 Apr 2005 22:13:13 +0200`). Dates without a time zone are in
+/// `local_offset` (minutes east of UTC).
+///
+/// # Errors
+///
+/// Returns an `Err(Error)` if the date is in none of these formats, or out
+/// of range.
+pub fn parse_date(value: &str, local_offset: i32) -> Result<Time, Error> {
+    let value = value.trim();
+    let invalid = || Error::new(ErrorCode::Invalid, ErrorClass::Invalid, format!("invalid date '{}'", value));
+    let number = |text: Option<&str>| text.map_or(Ok(0), |text| text.parse::<i64>().map_err(|_| invalid()));
+
+    if let Some(captures) = Regex::new(RAW_DATE_PATTERN).expect("valid raw date pattern").captures(value) {
+        let seconds = number(Some(&captures[1]))?;
+        let offset = match captures.get(2) {
+            Some(offset) => parse_offset(offset.as_str()).ok_or_else(invalid)?,
+            None => 0,
+        };
+        return Ok(Time::new(seconds, offset));
+    }
+
+    // year, month, day, hours, minutes, seconds and time zone
+    let (fields, zone) = if let Some(c) = Regex::new(ISO_DATE_PATTERN).expect("valid ISO date pattern").captures(value) {
+        let fields = [1, 2, 3, 4, 5, 6].map(|i| c.get(i).map(|m| m.as_str().to_string()));
+        (fields, c.get(7).map(|m| m.as_str().to_string()))
+    } else if let Some(c) = Regex::new(RFC2822_DATE_PATTERN).expect("valid RFC 2822 date pattern").captures(value) {
+        let month = MONTHS.iter().position(|m| m.eq_ignore_ascii_case(&c[2])).ok_or_else(invalid)? + 1;
+        let fields = [
+            Some(c[3].to_string()), Some(month.to_string()), Some(c[1].to_string()),
+            Some(c[4].to_string()), Some(c[5].to_string()), c.get(6).map(|m| m.as_str().to_string()),
+        ];
+        (fields, c.get(7).map(|m| m.as_str().to_string()))
+    } else {
+        return Err(invalid());
+    };
+
+    let [year, month, day, hours, minutes, seconds] = fields.map(|field| number(field.as_deref()));
+    let (year, month, day, hours, minutes, seconds) = (year?, month?, day?, hours?, minutes?, seconds?);
+    if !(1..=12).contains(&month) || hours > 23 || minutes > 59 || seconds > 60 {
+        return Err(invalid());
+    }
+    // `2005-02-31` must not roll over into March
+    if !(1..=days_in_month(year, month)).contains(&day) {
+        return Err(invalid());
+    }
+    let offset = match zone {
+        Some(zone) => parse_offset(&zone).ok_or_else(invalid)?,
+        None => local_offset,
+    };
+    let local = days_from_civil(year, month, day) * 86400 + hours * 3600 + minutes * 60 + seconds;
+    Ok(Time::new(local - i64::from(offset) * 60, offset))
+}
+
+/// The author and committer of a commit.
+///
+/// # Fields
+///
+/// * `author` - Who wrote the change.
+/// * `committer` - Who made the commit, whose identity also signs it off.
+pub struct Identities {
+    pub author: Signature<'static>,
+    pub committer: Signature<'static>,
+}
+
+// reads an environment variable; a function so that tests can provide their own
+type Env<'a> = &'a dyn Fn(&str) -> Option<String>;
+
+fn process_env(name: &str) -> Option<String> {
+    std::env::var(name).ok().filter(|value| !value.trim().is_empty())
+}
+
+fn unknown_identity() -> Error {
+    Error::new(ErrorCode::NotFound, ErrorClass::Config, "author identity unknown, run\n\n  \
+        git config user.email \"you@example.com\"\n  git config user.name \"Your Name\"\n\n\
+        to set it for this repository (or add --global to set it for every repository)")
+}
+
+// asks for the missing name and email, like git tells you to set them, and
+// offers to save them to the repository configuration
+fn prompt_identity(repo: &Repository, name: Option<String>, email: Option<String>) -> Result<(String, String), Error> {
+    let prompt_error = |e: inquire::InquireError| Error::new(ErrorCode::NotFound, ErrorClass::Invalid, e.to_string());
+    eprintln!("Author identity unknown: user.name and user.email are not both set.");
+    let name = match name {
+        Some(name) => name,
+        None => Text::new("Your name:").prompt().map_err(prompt_error)?,
+    };
+    let email = match email {
+        Some(email) => email,
+        None => Text::new("Your email:").prompt().map_err(prompt_error)?,
+    };
+
+    let save = Confirm::new("Save this identity in the repository configuration?")
+        .with_default(true)
+        .prompt()
+        .map_err(prompt_error)?;
+    if save {
+        let mut local = repo.config()?.open_level(ConfigLevel::Local)?;
+        local.set_str("user.name", name.trim())?;
+        local.set_str("user.email", email.trim())?;
+    }
+    Ok((name.trim().to_string(), email.trim().to_string()))
+}
+
+/// Resolves the author and committer of a commit like git does.
+///
+/// The committer comes from `GIT_COMMITTER_NAME`, `GIT_COMMITTER_EMAIL` and
+/// `GIT_COMMITTER_DATE`, and the author from the `GIT_AUTHOR_*` variables,
+/// both falling back to `user.name`, `user.email` and the current time.
+/// `previous` (the author of an amended commit) replaces the author
+/// defaults. `author` (`Name <email>`, or part of a previous author) and
+/// `date` override the author.
+///
+/// When the identity is missing, it is prompted for in interactive mode, and
+/// can be saved to the repository configuration.
+///
+/// # Errors
+///
+/// Returns an `Err(Error)` if:
+/// - The identity is missing in non-interactive mode, or the prompt fails.
+/// - `author` matches no previous author, or several.
+/// - A date cannot be parsed.
+pub fn resolve_identities(
+    repo: &Repository,
+    author: Option<&str>,
+    date: Option<&str>,
+    previous: Option<&Signature>,
+    interactive: bool,
+) -> Result<Identities, Error> {
+    resolve_with_env(repo, author, date, previous, interactive, &process_env)
+}
+
+fn resolve_with_env(
+    repo: &Repository,
+    author: Option<&str>,
+    date: Option<&str>,
+    previous: Option<&Signature>,
+    interactive: bool,
+    env: Env,
+) -> Result<Identities, Error> {
+    let config = repo.config()?;
+    let configured = |key: &str| config.get_string(key).ok().filter(|value| !value.trim().is_empty());
+    let mut user = (configured("user.name"), configured("user.email"));
+
+    // the configuration is only needed for what the environment leaves out
+    let author_given = author.is_some() || previous.is_some()
+        || (env("GIT_AUTHOR_NAME").is_some() && env("GIT_AUTHOR_EMAIL").is_some());
+    let committer_given = env("GIT_COMMITTER_NAME").is_some() && env("GIT_COMMITTER_EMAIL").is_some();
+    if !(author_given && committer_given) && (user.0.is_none() || user.1.is_none()) {
+        if !interactive {
+            return Err(unknown_identity());
+        }
+        let (name, email) = prompt_identity(repo, user.0, user.1)?;
+        user = (Some(name), Some(email));
+    }
+
+    let now = Signature::now("now", "now")?.when();
+    let when = |value: Option<String>| match value {
+        Some(value) => parse_date(&value, now.offset_minutes()),
+        None => Ok(now),
+    };
+
+    let committer = Signature::new(
+        &env("GIT_COMMITTER_NAME").or(user.0.clone()).unwrap_or_default(),
+        &env("GIT_COMMITTER_EMAIL").or(user.1.clone()).unwrap_or_default(),
+        &when(env("GIT_COMMITTER_DATE"))?,
+    )?;
+
+    let (mut name, mut email, mut time) = match previous {
+        Some(previous) => (
+            previous.name().unwrap_or_default().to_string(),
+            previous.email().unwrap_or_default().to_string(),
+            previous.when(),
+        ),
+        None => (
+            env("GIT_AUTHOR_NAME").or(user.0).unwrap_or_default(),
+            env("GIT_AUTHOR_EMAIL").or(user.1).unwrap_or_default(),
+            when(env("GIT_AUTHOR_DATE"))?,
+        ),
+    };
+    if let Some(author) = author {
+        // the history is only searched for part of an identity
+        let resolved = match full_identity(author) {
+            Some(identity) => identity,
+            None => resolve_author(author, &known_authors(repo, "")?)?,
+        };
+        (name, email) = split_identity(&resolved).unwrap_or((resolved, String::new()));
+    }
+    if let Some(date) = date {
+        time = parse_date(date, now.offset_minutes())?;
+    }
+
+    Ok(Identities { author: Signature::new(&name, &email, &time)?, committer })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_parse_date() {
+        let expected = Time::new(1112904793, 120);
+        for date in [
+            "@1112904793 +0200",
+            "1112904793 +02:00",
+            "2005-04-07T22:13:13+02:00",
+            "2005-04-07 20:13:13Z",
+            "Thu, 07 Apr 2005 22:13:13 +0200",
+        ] {
+            let time = parse_date(date, 0).unwrap();
+            assert_eq!(time.seconds(), expected.seconds(), "{}", date);
+        }
+        assert_eq!(parse_date("2005-04-07T22:13:13+02:00", 0).unwrap().offset_minutes(), 120);
+
+        // without a time zone, the local offset applies
+        let time = parse_date("2005-04-07 22:13:13", 120).unwrap();
+        assert_eq!((time.seconds(), time.offset_minutes()), (1112904793, 120));
+        assert_eq!(parse_date("1970-01-02", 0).unwrap().seconds(), 86400);
+        assert_eq!(parse_date("@0", 60).unwrap().offset_minutes(), 0);
+
+        assert!(parse_date("yesterday", 0).is_err());
+        assert!(parse_date("2005-13-07", 0).is_err());
+        for impossible in ["2005-02-31", "2005-04-31", "2005-02-29", "1900-02-29", "31 Jun 2005 10:00:00 +0000"] {
+            assert!(parse_date(impossible, 0).is_err(), "{}", impossible);
+        }
+        assert_eq!(parse_date("2004-02-29", 0).unwrap().seconds(), 1078012800);
+        assert_eq!(parse_date("2000-02-29", 0).unwrap().seconds(), 951782400);
+        assert!(parse_date("2005-12-31", 0).is_ok());
+        assert!(parse_date("07 Foo 2005 22:13:13", 0).is_err());
+    }
+
+    #[test]
+    fn test_format_date() {
+        assert_eq!(format_date(&Time::new(1112904793, 120)), "2005-04-07 22:13:13 +0200");
+        assert_eq!(format_date(&Time::new(0, -90)), "1969-12-31 22:30:00 -0130");
+        assert_eq!(format_date(&Time::new(951782400, 0)), "2000-02-29 00:00:00 +0000");
+    }
+
+    #[test]
+    fn test_resolve_identities() {
+        let temp_dir = tempdir().unwrap();
+        let repo = Repository::init(temp_dir.path()).unwrap();
+        let no_env = |_: &str| None;
+        let mut config = repo.config().unwrap();
+        // blank values hide a global identity
+        config.set_str("user.name", "").unwrap();
+        config.set_str("user.email", "").unwrap();
+
+        assert_eq!(
+            resolve_with_env(&repo, None, None, None, false, &no_env).err().map(|e| e.code()),
+            Some(ErrorCode::NotFound)
+        );
+
+        let vars: HashMap<&str, &str> = HashMap::from([
+            ("GIT_AUTHOR_NAME", "Alice"),
+            ("GIT_AUTHOR_EMAIL", "alice@example.com"),
+            ("GIT_AUTHOR_DATE", "@1112904793 +0200"),
+            ("GIT_COMMITTER_NAME", "Bob"),
+            ("GIT_COMMITTER_EMAIL", "bob@example.com"),
+        ]);
+        let env = |name: &str| vars.get(name).map(|value| value.to_string());
+        let identities = resolve_with_env(&repo, None, None, None, false, &env).unwrap();
+        assert_eq!(identities.author.name(), Some("Alice"));
+        assert_eq!(identities.author.when().seconds(), 1112904793);
+        assert_eq!(identities.committer.email(), Some("bob@example.com"));
+
+        config.set_str("user.name", "Test User").unwrap();
+        config.set_str("user.email", "test@example.com").unwrap();
+        let identities = resolve_with_env(&repo, None, Some("2005-04-07 22:13:13 +0200"), None, false, &no_env).unwrap();
+        assert_eq!(identities.author.name(), Some("Test User"));
+        assert_eq!(identities.author.when().seconds(), 1112904793);
+        assert_eq!(identities.committer.name(), Some("Test User"));
+
+        let previous = Signature::new("Carol", "carol@example.com", &Time::new(0, 0)).unwrap();
+        let identities = resolve_with_env(&repo, None, None, Some(&previous), false, &env).unwrap();
+        assert_eq!(identities.author.name(), Some("Carol"));
+        assert_eq!(identities.author.when().seconds(), 0);
+
+        let identities = resolve_with_env(&repo, Some("Dan <dan@example.com>"), None, Some(&previous), false, &env).unwrap();
+        assert_eq!(identities.author.email(), Some("dan@example.com"));
+        assert_eq!(identities.author.when().seconds(), 0);
+        assert!(resolve_with_env(&repo, Some("nobody"), None, None, false, &no_env).is_err());
+
+        // a full identity is used without walking the history, which here
+        // points to a missing commit
+        let head = repo.find_reference("HEAD").unwrap();
+        let branch = head.symbolic_target().unwrap();
+        std::fs::write(repo.path().join(branch), format!("{}\n", "1".repeat(40))).unwrap();
+        assert!(known_authors(&repo, "").is_err());
+        let identities = resolve_with_env(&repo, Some("Dan <dan@example.com>"), None, None, false, &no_env).unwrap();
+        assert_eq!(identities.author.name(), Some("Dan"));
+    }
+}
@@ -0,0 +1,100 @@
+use git2::{Error, Repository};
+use regex::Regex;
+
+/// Returns the short name of the current branch, also when it has no commit
+/// yet, or `None` when `HEAD` is detached.
+pub fn current_branch(repo: &Repository) -> Option<String> {
+    if let Ok(head) = repo.head() {
+        return match head.is_branch() {
+            true => head.shorthand().map(str::to_string),
+            false => None,
+        };
+    }
+    // an unborn branch: HEAD still points to it symbolically
+    repo.find_reference("HEAD").ok()?
+        .symbolic_target()?
+        .strip_prefix("refs/heads/")
+        .map(str::to_string)
+}
+
+/// Extracts the issue key from a branch name: the first group of `pattern`
+/// when it has one, the whole match otherwise.
+///
+/// # Errors
+///
+/// Returns an `Err(Error)` if `pattern` is not a valid regular expression.
+pub fn issue_from_branch(branch: &str, pattern: &str) -> Result<Option<String>, Error> {
+    let regex = Regex::new(pattern)
+        .map_err(|e| Error::from_str(format!("invalid issue pattern '{}': {}", pattern, e).as_str()))?;
+    Ok(regex.captures(branch).and_then(|captures| {
+        captures.get(1).or(captures.get(0)).map(|key| key.as_str().to_string())
+    }))
+}
+
+/// Returns whether a text mentions an issue key as a whole: `PROJ-4` is not
+/// mentioned by `PROJ-42`, nor `#1` by `#12`.
+pub fn mentions_issue(text: &str, key: &str) -> bool {
+    // `\b` does not fit keys starting or ending with a symbol (`#12`)
+    let pattern = format!(r"(?:^|[^A-Za-z0-9_]){}(?:$|[^A-Za-z0-9_])", regex::escape(key));
+    Regex::new(&pattern).is_ok_and(|regex| regex.is_match(text))
+}
+
+/// Returns whether a branch name matches one of the patterns, where `*`
+/// matches any sequence of characters (`release/*`).
+pub fn branch_matches(branch: &str, patterns: &[String]) -> bool {
+    patterns.iter().any(|pattern| {
+        let source = pattern.split('*').map(regex::escape).collect::<Vec<String>>().join(".*");
+        Regex::new(&format!("^{}$", source)).is_ok_and(|regex| regex.is_match(branch))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use git2::Signature;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_current_branch() {
+        let temp_dir = tempdir().unwrap();
+        let repo = Repository::init(temp_dir.path()).unwrap();
+        repo.set_head("refs/heads/feature/PROJ-421-login").unwrap();
+        assert_eq!(current_branch(&repo).as_deref(), Some("feature/PROJ-421-login"));
+
+        let signature = Signature::now("Test User", "test@example.com").unwrap();
+        let tree = repo.find_tree(repo.index().unwrap().write_tree().unwrap()).unwrap();
+        let oid = repo.commit(Some("HEAD"), &signature, &signature, "🎉 begin\n", &tree, &[]).unwrap();
+        assert_eq!(current_branch(&repo).as_deref(), Some("feature/PROJ-421-login"));
+
+        repo.set_head_detached(oid).unwrap();
+        assert_eq!(current_branch(&repo), None);
+    }
+
+    #[test]
+    fn test_issue_from_branch() {
+        let pattern = "[A-Z][A-Z0-9]+-[0-9]+";
+        assert_eq!(issue_from_branch("feature/PROJ-421-login", pattern).unwrap().as_deref(), Some("PROJ-421"));
+        assert_eq!(issue_from_branch("main", pattern).unwrap(), None);
+        assert_eq!(issue_from_branch("fix/gh-12-crash", "gh-([0-9]+)").unwrap().as_deref(), Some("12"));
+        assert!(issue_from_branch("main", "(").is_err());
+    }
+
+    #[test]
+    fn test_mentions_issue() {
+        assert!(mentions_issue("PROJ-4 fix crash", "PROJ-4"));
+        assert!(mentions_issue("fix crash (PROJ-4)", "PROJ-4"));
+        assert!(!mentions_issue("PROJ-42 fix crash", "PROJ-4"));
+        assert!(!mentions_issue("XPROJ-4 fix crash", "PROJ-4"));
+        assert!(mentions_issue("fix #12", "#12"));
+        assert!(!mentions_issue("fix #123", "#12"));
+    }
+
+    #[test]
+    fn test_branch_matches() {
+        let patterns = vec!["main".to_string(), "release/*".to_string()];
+        assert!(branch_matches("main", &patterns));
+        assert!(branch_matches("release/1.2", &patterns));
+        assert!(!branch_matches("mainline", &patterns));
+        assert!(!branch_matches("feature/x", &patterns));
+    }
+}
@@ -0,0 +1,488 @@
+use std::collections::BTreeSet;
+use std::fmt;
+use std::io::Read;
+use git2::{Error, ErrorClass, ErrorCode, Repository};
+use regex::Regex;
+use crate::types::config::Config;
+use crate::types::emoji::EmojiCatalog;
+use crate::types::repository::{comment_char, get_current_repository};
+use crate::types::template::{strip_autosquash, Template, HEADER_PATTERN};
+
+/// Rule ids reported by the linter.
+pub const EMPTY_MESSAGE: &str = "empty-message";
+pub const LEADING_EMOJI: &str = "leading-emoji";
+pub const SUBJECT_LENGTH: &str = "subject-length";
+pub const BLANK_SECOND_LINE: &str = "blank-second-line";
+pub const CONVENTIONAL_TYPE: &str = "conventional-type";
+pub const SCOPE_ALLOWED: &str = "scope-allowed";
+pub const SUBJECT_TRAILING_PERIOD: &str = "subject-trailing-period";
+pub const BREAKING_FOOTER: &str = "breaking-footer";
+
+// a line shaped like a breaking change footer (a token followed by `:` or
+// ` #`), in any spelling, well-formed or not; prose mentioning breaking
+// changes is left alone
+const BREAKING_PATTERN: &str = r"(?i)^\s*breaking[ _-]?changes?(?:\s*:|\s+#)";
+
+// git's scissors line: everything below it is dropped from the message
+const SCISSORS: &str = " ------------------------ >8 ------------------------";
+
+/// How serious a lint finding is.
+///
+/// # Possible values
+///
+/// * `Error` - The message breaks a rule; `glyphit lint` fails.
+/// * `Warning` - The message is accepted, but could be improved.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// A lint finding in a commit message.
+///
+/// # Fields
+///
+/// * `rule` - The id of the rule, e.g. `subject-length`.
+/// * `line` - The 1-based line of the message the finding points to.
+/// * `column` - The 1-based column, in characters.
+/// * `severity` - Whether the finding is an error or a warning.
+/// * `message` - A human readable description.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub rule: &'static str,
+    pub line: usize,
+    pub column: usize,
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}[{}]: {}", self.line, self.column, self.severity, self.rule, self.message)
+    }
+}
+
+impl Diagnostic {
+    fn new(rule: &'static str, line: usize, column: usize, severity: Severity, message: String) -> Self {
+        Self { rule, line, column, severity, message }
+    }
+}
+
+/// Checks commit messages against the configured rules.
+///
+/// The checks are: a known (and allowed) leading emoji, the subject length
+/// limit, a blank second line, a known conventional type and an allowed scope
+/// (when the template has a `{type}`), no trailing period on the subject and a
+/// well-formed `BREAKING CHANGE: description` footer.
+#[derive(Debug, Clone)]
+pub struct Linter {
+    config: Config,
+    catalog: EmojiCatalog,
+    template: Template,
+    comment_char: char,
+}
+
+impl Linter {
+    /// Creates a linter for the given configuration.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `Err(Error)` if the emoji catalog or the message template
+    /// cannot be loaded.
+    pub fn new(config: Config) -> Result<Self, Error> {
+        let catalog = EmojiCatalog::load(&config)?;
+        let template = Template::resolve(&config.template)?;
+        Ok(Self { config, catalog, template, comment_char: '#' })
+    }
+
+    /// Creates a linter for the configuration of a repository, honouring its
+    /// `core.commentChar`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `Err(Error)` if the configuration, the emoji catalog or the
+    /// message template cannot be loaded.
+    pub fn load(repo: &Repository) -> Result<Self, Error> {
+        let mut linter = Self::new(Config::load(repo)?)?;
+        linter.comment_char = comment_char(repo)?;
+        Ok(linter)
+    }
+
+    /// Returns the configuration the linter checks against.
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Lints a commit message, returning the findings in line order.
+    ///
+    /// Comment lines and everything below git's scissors line are ignored,
+    /// so the file passed to a `commit-msg` hook can be linted as it is.
+    pub fn lint(&self, message: &str) -> Vec<Diagnostic> {
+        self.lint_lines(message, true)
+    }
+
+    /// Lints the message of an existing commit, returning the findings in
+    /// line order.
+    ///
+    /// Unlike [`Linter::lint`], every line is checked as it is stored: a line
+    /// starting with the comment character (`#123 was the root cause`) or
+    /// looking like a scissors line is part of the message.
+    pub fn lint_committed(&self, message: &str) -> Vec<Diagnostic> {
+        self.lint_lines(message, false)
+    }
+
+    // lints a message, dropping its comments and scissors first when it is
+    // still being edited
+    fn lint_lines(&self, message: &str, edited: bool) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+
+        let scissors = format!("{}{}", self.comment_char, SCISSORS);
+        let mut lines: Vec<(usize, &str)> = message.lines()
+            .enumerate()
+            .take_while(|(_, line)| !edited || *line != scissors)
+            .filter(|(_, line)| !edited || !line.starts_with(self.comment_char))
+            .map(|(i, line)| (i + 1, line))
+            .skip_while(|(_, line)| line.trim().is_empty())
+            .collect();
+        while lines.last().is_some_and(|(_, line)| line.trim().is_empty()) {
+            lines.pop();
+        }
+
+        let (subject_line, subject) = match lines.first() {
+            Some(&first) => first,
+            None => {
+                diagnostics.push(Diagnostic::new(EMPTY_MESSAGE, 1, 1, Severity::Error, "the message is empty".to_string()));
+                return diagnostics;
+            }
+        };
+
+        // a `fixup! `/`squash! ` subject is linted as the subject it targets,
+        // with the columns still counted from the start of the line
+        let target = strip_autosquash(subject);
+        let prefix = subject[..subject.len() - target.len()].chars().count();
+        let first = diagnostics.len();
+        self.lint_subject(subject_line, target, &mut diagnostics);
+        for diagnostic in &mut diagnostics[first..] {
+            diagnostic.column += prefix;
+        }
+
+        if let Some(&(line, second)) = lines.get(1)
+            && !second.trim().is_empty() {
+            diagnostics.push(Diagnostic::new(
+                BLANK_SECOND_LINE, line, 1, Severity::Error,
+                "the subject must be followed by a blank line".to_string(),
+            ));
+        }
+
+        lint_breaking_footers(&lines[1..], &mut diagnostics);
+
+        diagnostics
+    }
+
+    fn lint_subject(&self, line: usize, subject: &str, diagnostics: &mut Vec<Diagnostic>) {
+        let length = subject.chars().count();
+        if length > self.config.subject_max_length {
+            diagnostics.push(Diagnostic::new(
+                SUBJECT_LENGTH, line, self.config.subject_max_length + 1, Severity::Error,
+                format!("the subject is {} characters long, the limit is {}", length, self.config.subject_max_length),
+            ));
+        }
+
+        let trimmed = subject.trim_end();
+        if trimmed.ends_with('.') {
+            diagnostics.push(Diagnostic::new(
+                SUBJECT_TRAILING_PERIOD, line, trimmed.chars().count(), Severity::Warning,
+                "the subject should not end with a period".to_string(),
+            ));
+        }
+
+        let (emoji, prefix_len) = match self.catalog.match_prefix(subject) {
+            Some(found) => found,
+            None => {
+                diagnostics.push(Diagnostic::new(
+                    LEADING_EMOJI, line, 1, Severity::Error,
+                    "the subject must start with a known emoji".to_string(),
+                ));
+                return;
+            }
+        };
+
+        if !self.template.uses("type") {
+            return;
+        }
+
+        let rest = &subject[prefix_len..];
+        let offset = subject.len() - rest.trim_start().len();
+        let rest = rest.trim_start();
+        let column = |byte: usize| subject[..offset + byte].chars().count() + 1;
+        let header = Regex::new(HEADER_PATTERN).expect("valid header pattern");
+
+        let captures = match header.captures(rest) {
+            Some(captures) => captures,
+            None => {
+                if let Some(expected) = &emoji.conventional_type {
+                    diagnostics.push(Diagnostic::new(
+                        CONVENTIONAL_TYPE, line, column(0), Severity::Error,
+                        format!("missing conventional type, expected '{}' for :{}:", expected, emoji.shortcode),
+                    ));
+                }
+                return;
+            }
+        };
+
+        let commit_type = captures.get(1).expect("type group");
+        let known: BTreeSet<&str> = self.catalog.emojis().iter()
+            .filter_map(|e| e.conventional_type.as_deref())
+            .collect();
+        if !known.contains(commit_type.as_str()) {
+            diagnostics.push(Diagnostic::new(
+                CONVENTIONAL_TYPE, line, column(commit_type.start()), Severity::Error,
+                format!(
+                    "unknown conventional type '{}', expected one of: {}",
+                    commit_type.as_str(), known.into_iter().collect::<Vec<&str>>().join(", ")
+                ),
+            ));
+        } else if let Some(expected) = &emoji.conventional_type
+            && expected != commit_type.as_str() {
+            diagnostics.push(Diagnostic::new(
+                CONVENTIONAL_TYPE, line, column(commit_type.start()), Severity::Warning,
+                format!("type '{}' does not match :{}:, which is '{}'", commit_type.as_str(), emoji.shortcode, expected),
+            ));
+        }
+
+        if let Some(scope) = captures.get(2) {
+            if scope.as_str().trim().is_empty() {
+                diagnostics.push(Diagnostic::new(
+                    SCOPE_ALLOWED, line, column(scope.start()), Severity::Error,
+                    "the scope is empty".to_string(),
+                ));
+            } else if let Some(allowed) = &self.config.scopes
+                && !allowed.iter().any(|a| a == scope.as_str()) {
+                diagnostics.push(Diagnostic::new(
+                    SCOPE_ALLOWED, line, column(scope.start()), Severity::Error,
+                    format!("scope '{}' is not allowed, expected one of: {}", scope.as_str(), allowed.join(", ")),
+                ));
+            }
+        }
+    }
+}
+
+// a breaking change footer is spelled `BREAKING CHANGE: description` (or
+// `BREAKING-CHANGE`), and belongs to the last paragraph of the message
+fn lint_breaking_footers(lines: &[(usize, &str)], diagnostics: &mut Vec<Diagnostic>) {
+    let breaking = Regex::new(BREAKING_PATTERN).expect("valid breaking pattern");
+    let last_paragraph = lines.iter()
+        .rposition(|(_, line)| line.trim().is_empty())
+        .map(|blank| blank + 1)
+        .unwrap_or(0);
+
+    for (index, &(line, text)) in lines.iter().enumerate() {
+        if !breaking.is_match(text) {
+            continue;
+        }
+        let well_formed = ["BREAKING CHANGE: ", "BREAKING-CHANGE: "].iter()
+            .any(|token| text.strip_prefix(token).is_some_and(|value| !value.trim().is_empty()));
+        if !well_formed {
+            diagnostics.push(Diagnostic::new(
+                BREAKING_FOOTER, line, 1, Severity::Error,
+                "breaking change footers must read 'BREAKING CHANGE: <description>'".to_string(),
+            ));
+        } else if index < last_paragraph {
+            diagnostics.push(Diagnostic::new(
+                BREAKING_FOOTER, line, 1, Severity::Error,
+                "the breaking change footer must be in the last paragraph".to_string(),
+            ));
+        }
+    }
+}
+
+/// Lints a commit message read from a file, or from the standard input when
+/// `file` is `None` or `-`, and prints the findings.
+///
+/// # Arguments
+///
+/// * `repo` - An optional reference to a `Repository`. If `None`, the function
+///   attempts to find the current repository automatically.
+/// * `file` - The path of the message file, or `None`/`-` for the standard input.
+///
+/// # Errors
+///
+/// Returns an `Err(Error)` if:
+/// - The current repository cannot be determined.
+/// - The configuration, emoji catalog or template cannot be loaded.
+/// - The message cannot be read.
+/// - The message has at least one error-level finding.
+///
+/// # Returns
+///
+/// * `Ok(())` if the message has no errors (warnings are printed but allowed).
+pub fn lint(repo: Option<&Repository>, file: Option<&str>) -> Result<(), Error> {
+    let owned_repo;
+    let current_repo = match repo {
+        Some(r) => r,
+        _ => {
+            owned_repo = get_current_repository()?;
+            &owned_repo
+        }
+    };
+    let linter = Linter::load(current_repo)?;
+
+    let (source, message) = match file {
+        None | Some("-") => {
+            let mut message = String::new();
+            std::io::stdin().read_to_string(&mut message)
+                .map_err(|e| Error::from_str(format!("cannot read the standard input: {}", e).as_str()))?;
+            ("<stdin>", message)
+        }
+        Some(path) => (path, std::fs::read_to_string(path)
+            .map_err(|e| Error::from_str(format!("cannot read {}: {}", path, e).as_str()))?),
+    };
+
+    let diagnostics = linter.lint(&message);
+    for diagnostic in &diagnostics {
+        println!("{}:{}", source, diagnostic);
+    }
+
+    let errors = diagnostics.iter().filter(|d| d.severity == Severity::Error).count();
+    if errors > 0 {
+        return Err(Error::new(ErrorCode::Invalid, ErrorClass::Invalid, format!(
+            "{} error(s), {} warning(s) in the commit message", errors, diagnostics.len() - errors
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+    use crate::types::template::CONVENTIONAL;
+
+    fn rules(diagnostics: &[Diagnostic]) -> Vec<(&'static str, usize, usize, Severity)> {
+        diagnostics.iter().map(|d| (d.rule, d.line, d.column, d.severity)).collect()
+    }
+
+    fn conventional() -> Linter {
+        Linter::new(Config {
+            template: CONVENTIONAL.to_string(),
+            scopes: Some(vec!["cli".to_string(), "parser".to_string()]),
+            ..Config::default()
+        }).unwrap()
+    }
+
+    #[test]
+    fn test_valid_messages() {
+        let linter = Linter::new(Config::default()).unwrap();
+        assert!(linter.lint("✨ add login form\n").is_empty());
+        assert!(linter.lint(":bug: fix crash\n\nDetails.\n\nBREAKING CHANGE: the API changed\n").is_empty());
+
+        assert!(conventional().lint("✨ feat(cli)!: add login form\n\nBREAKING-CHANGE: flags renamed\n").is_empty());
+        assert!(conventional().lint("🎉 chore: begin a project\n").is_empty());
+        assert!(conventional().lint("fixup! squash! ✨ feat(cli): add login form\n").is_empty());
+    }
+
+    #[test]
+    fn test_comments_and_scissors_are_ignored() {
+        let linter = Linter::new(Config::default()).unwrap();
+        let message = "# Please enter the commit message\n\n✨ add login form\n# a comment\n\n\
+            # ------------------------ >8 ------------------------\nnot part of the message\n";
+
+        assert!(linter.lint(message).is_empty());
+        assert_eq!(rules(&linter.lint("# only a comment\n")), vec![(EMPTY_MESSAGE, 1, 1, Severity::Error)]);
+    }
+
+    #[test]
+    fn test_committed_messages_are_linted_as_stored() {
+        let linter = Linter::new(Config::default()).unwrap();
+
+        // in a commit, `#123` starts a line of the message, not a comment
+        let message = "🐛 fix crash\n#123 was the root cause\n";
+        assert!(linter.lint(message).is_empty());
+        assert_eq!(rules(&linter.lint_committed(message)), vec![(BLANK_SECOND_LINE, 2, 1, Severity::Error)]);
+
+        let message = "🐛 fix crash\n\n# ------------------------ >8 ------------------------\n\nBREAKING CHANGE: gone\n\nend\n";
+        assert!(linter.lint(message).is_empty());
+        assert_eq!(rules(&linter.lint_committed(message)), vec![(BREAKING_FOOTER, 5, 1, Severity::Error)]);
+        assert!(linter.lint_committed("🐛 fix crash\n\n#123 was the root cause\n").is_empty());
+    }
+
+    #[test]
+    fn test_subject_rules() {
+        let linter = Linter::new(Config { subject_max_length: 20, ..Config::default() }).unwrap();
+
+        assert_eq!(
+            rules(&linter.lint("add a rather long login form.\nbody\n")),
+            vec![
+                (SUBJECT_LENGTH, 1, 21, Severity::Error),
+                (SUBJECT_TRAILING_PERIOD, 1, 29, Severity::Warning),
+                (LEADING_EMOJI, 1, 1, Severity::Error),
+                (BLANK_SECOND_LINE, 2, 1, Severity::Error),
+            ]
+        );
+        assert_eq!(
+            rules(&linter.lint("fixup! add login form.\n")),
+            vec![(SUBJECT_TRAILING_PERIOD, 1, 22, Severity::Warning), (LEADING_EMOJI, 1, 8, Severity::Error)]
+        );
+    }
+
+    #[test]
+    fn test_conventional_rules() {
+        let linter = conventional();
+
+        assert_eq!(rules(&linter.lint("✨ add login form\n")), vec![(CONVENTIONAL_TYPE, 1, 3, Severity::Error)]);
+        assert_eq!(rules(&linter.lint("✨ feature: add login form\n")), vec![(CONVENTIONAL_TYPE, 1, 3, Severity::Error)]);
+        assert_eq!(rules(&linter.lint("✨ fix: add login form\n")), vec![(CONVENTIONAL_TYPE, 1, 3, Severity::Warning)]);
+        assert_eq!(rules(&linter.lint("✨ feat(web): add login form\n")), vec![(SCOPE_ALLOWED, 1, 8, Severity::Error)]);
+        assert_eq!(rules(&linter.lint("✨ feat(): add login form\n")), vec![(SCOPE_ALLOWED, 1, 8, Severity::Error)]);
+    }
+
+    #[test]
+    fn test_breaking_footer_rules() {
+        let linter = Linter::new(Config::default()).unwrap();
+
+        assert_eq!(
+            rules(&linter.lint("✨ add login form\n\nBreaking change: flags renamed\n")),
+            vec![(BREAKING_FOOTER, 3, 1, Severity::Error)]
+        );
+        assert_eq!(
+            rules(&linter.lint("✨ add login form\n\nBREAKING CHANGE:\n")),
+            vec![(BREAKING_FOOTER, 3, 1, Severity::Error)]
+        );
+        assert_eq!(
+            rules(&linter.lint("✨ add login form\n\nBREAKING CHANGE: flags renamed\n\nMore details.\n")),
+            vec![(BREAKING_FOOTER, 3, 1, Severity::Error)]
+        );
+        assert_eq!(
+            rules(&linter.lint("✨ add login form\n\nbreaking-changes #12\n")),
+            vec![(BREAKING_FOOTER, 3, 1, Severity::Error)]
+        );
+
+        // prose about breaking changes is not a footer
+        let message = "✨ add login form\n\nBreaking changes were avoided by keeping the old API.\n\nRefs: #12\n";
+        assert!(linter.lint(message).is_empty());
+        assert!(linter.lint("✨ add login form\n\nBreaking change ahead, see the notes.\n").is_empty());
+    }
+
+    #[test]
+    fn test_lint_file() {
+        let temp_dir = tempdir().unwrap();
+        let repo = Repository::init(temp_dir.path()).unwrap();
+        let valid = temp_dir.path().join("valid.txt");
+        let invalid = temp_dir.path().join("invalid.txt");
+        std::fs::write(&valid, "🐛 fix crash\n").unwrap();
+        std::fs::write(&invalid, "fix crash\n").unwrap();
+
+        assert!(lint(Some(&repo), valid.to_str()).is_ok());
+        assert!(lint(Some(&repo), invalid.to_str()).is_err());
+        assert!(lint(Some(&repo), Some("missing.txt")).is_err());
+    }
+}
@@ -1,3 +1,15 @@
 pub mod add;
+pub mod check;
 pub mod commit;
-pub mod push;
\ No newline at end of file
+pub mod editor;
+pub mod history;
+pub mod hooks;
+pub mod identity;
+pub mod issue;
+pub mod lint;
+pub mod push;
+pub mod scope;
+pub mod search;
+pub mod sign;
+pub mod suggest;
+pub mod trailers;
\ No newline at end of file
@@ -1,5 +1,8 @@
+use crate::functions::hooks::run_hook_with_input;
+use crate::types::commands::PushArgs;
+use crate::types::config::Config as GlyphitConfig;
 use crate::types::repository::get_current_repository;
-use git2::{Config, Cred, Error, PushOptions, RemoteCallbacks, Repository};
+use git2::{Config, Cred, Error, Oid, PushOptions, RemoteCallbacks, Repository};
 
 // function to create remote callbacks for a https repo url
 fn create_https_callback(repo_config: Config) -> RemoteCallbacks<'static> {
@@ -22,77 +25,83 @@ fn create_ssh_callbacks() -> RemoteCallbacks<'static> {
     callbacks
 }
 
-/// Pushes the current branch to the remote "origin" repository.
+/// Pushes the current branch to the default remote repository.
 ///
 /// This function attempts to push the HEAD branch of the provided `repo`
-/// to its "origin" remote. If no repository is provided (`None`),
+/// to the remote named by the configured `default_remote` (`origin` unless
+/// configured otherwise). If no repository is provided (`None`),
 /// it will try to discover and use the current local repository.
 ///
 /// # Arguments
 ///
 /// * `repo` - An optional reference to a `Repository`. If `None`,
-///            the function tries to find the current repository automatically.
+///   the function tries to find the current repository automatically.
+/// * `args` - The command-line options. Unless `args.no_verify` is set, the
+///   `pre-push` hook runs first, with the remote name and URL as arguments
+///   and `<local ref> <local sha> <remote ref> <remote sha>` on its standard
+///   input, and aborts the push when it fails.
 ///
 /// # Errors
 ///
-/// Returns an `Err(String)` if:
+/// Returns an `Err(Error)` if:
 /// - The current repository cannot be determined.
 /// - The HEAD reference cannot be retrieved.
-/// - The "origin" remote cannot be found.
+/// - The glyphit configuration cannot be loaded.
+/// - The default remote cannot be found.
+/// - The `pre-push` hook fails.
 /// - The push operation itself fails.
 ///
 /// # Returns
 ///
-/// * `Ok(0)` on successful push.
-pub fn push(repo: Option<&Repository>) -> Result<(), Error> {
+/// * `Ok(())` on successful push.
+pub fn push(repo: Option<&Repository>, args: &PushArgs) -> Result<(), Error> {
     let owned_repo;
     let current_repo = match repo {
         Some(r) => r,
         _ => {
-            owned_repo = get_current_repository().map_err(|e| e)?;
+            owned_repo = get_current_repository()?;
             &owned_repo
         }
     };
 
-    let head = match current_repo.head() {
-        Ok(h) => h,
-        Err(e) => return Err(e),
-    };
+    let head = current_repo.head()?;
     let branch = head.shorthand().unwrap();
     let refspec = format!("refs/heads/{}:refs/heads/{}", branch, branch);
 
     let mut push_options = PushOptions::new();
 
-    let repo_configuration = match current_repo.config() {
-        Ok(config) => config,
-        Err(e) => return Err(e)
-    };
+    let repo_configuration = current_repo.config()?;
+    let remote_name = GlyphitConfig::load(current_repo)?.default_remote;
 
-    let url = repo_configuration.get_string("remote.origin.url")?.to_string();
+    let url = repo_configuration.get_string(format!("remote.{}.url", remote_name).as_str())?.to_string();
     if !url.contains("https") {
         let callbacks = create_ssh_callbacks();
         push_options.remote_callbacks(callbacks);
     } else {
-        let config = match current_repo.config() {
-            Ok(config) => config,
-            Err(e) => return Err(e)
-        };
+        let config = current_repo.config()?;
         let callbacks = create_https_callback(config);
         push_options.remote_callbacks(callbacks);
     }
 
-    let mut origin = match current_repo.find_remote("origin") {
-        Ok(org) => org,
-        Err(e) => return Err(e),
-    };
+    let mut remote = current_repo.find_remote(&remote_name)?;
 
-    origin.push(&[refspec], Some(&mut push_options))
+    if !args.no_verify {
+        // the remote sha is the last known one, or zeros for a new branch
+        let local_sha = head.peel_to_commit()?.id();
+        let remote_sha = current_repo.refname_to_id(format!("refs/remotes/{}/{}", remote_name, branch).as_str())
+            .unwrap_or_else(|_| Oid::zero());
+        let input = format!("refs/heads/{} {} refs/heads/{} {}\n", branch, local_sha, branch, remote_sha);
+        run_hook_with_input(current_repo, "pre-push", &[&remote_name, &url], &[], Some(&input))?;
+    }
+
+    remote.push(&[refspec], Some(&mut push_options))
 }
 
 #[cfg(test)]
 mod tests {
     use crate::functions::add::add;
     use crate::functions::commit::commit;
+    use crate::types::commands::{CommitArgs, PushArgs};
     use crate::functions::push::push;
     use git2::{Repository, Signature};
     use std::fs::File;
@@ -106,17 +115,16 @@ mod tests {
         let file_path = temp_dir.path().join("initial.txt");
         File::create(&file_path).unwrap();
 
-        add(&vec!["initial.txt".to_string()], Some(&repo)).unwrap();
+        add(&["initial.txt".to_string()], Some(&repo)).unwrap();
 
         let mut config = repo.config().unwrap();
         config.set_str("user.name", "Test User").unwrap();
         config.set_str("user.email", "test@example.com").unwrap();
 
-        let tree;
         let signature = Signature::now("Test User", "test@example.com").unwrap();
         let mut index = repo.index().unwrap();
         let tree_id = index.write_tree().unwrap();
-        tree = repo.find_tree(tree_id).unwrap();
+        let tree = repo.find_tree(tree_id).unwrap();
         repo.commit(
             Some("HEAD"),
             &signature,
@@ -127,11 +135,65 @@ mod tests {
         )
         .unwrap();
 
-        let _ = commit(Some(&repo), true);
+        let args = CommitArgs {
+            emoji: Some(":tada:".to_string()),
+            title: Some("unit testing".to_string()),
+            no_interactive: true,
+            allow_empty: true,
+            ..Default::default()
+        };
+        let _ = commit(Some(&repo), &args);
 
-        let result = push(Some(&repo));
+        let result = push(Some(&repo), &PushArgs::default());
 
         // we expect a fail due the absence of a real remote
         assert!(result.is_err());
     }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_push_runs_pre_push_hook() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = tempdir().unwrap();
+        let remote_dir = tempdir().unwrap();
+        let repo = Repository::init(temp_dir.path()).unwrap();
+        Repository::init_bare(remote_dir.path()).unwrap();
+        let url = remote_dir.path().to_str().unwrap().to_string();
+        repo.remote("origin", &url).unwrap();
+
+        let mut config = repo.config().unwrap();
+        config.set_str("user.name", "Test User").unwrap();
+        config.set_str("user.email", "test@example.com").unwrap();
+        let args = CommitArgs {
+            emoji: Some(":tada:".to_string()),
+            title: Some("unit testing".to_string()),
+            no_interactive: true,
+            allow_empty: true,
+            ..Default::default()
+        };
+        let oid = commit(Some(&repo), &args).unwrap();
+        let branch = repo.head().unwrap().shorthand().unwrap().to_string();
+
+        // the hook records its arguments and input, then rejects the push
+        let hook = repo.path().join("hooks").join("pre-push");
+        std::fs::create_dir_all(hook.parent().unwrap()).unwrap();
+        std::fs::write(&hook, "#!/bin/sh\necho \"$1 $2\" > pre-push.log\ncat >> pre-push.log\nexit 1\n").unwrap();
+        std::fs::set_permissions(&hook, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        assert!(push(Some(&repo), &PushArgs::default()).is_err());
+        assert_eq!(
+            std::fs::read_to_string(temp_dir.path().join("pre-push.log")).unwrap(),
+            format!(
+                "origin {}\nrefs/heads/{} {} refs/heads/{} {}\n",
+                url, branch, oid, branch, git2::Oid::zero()
+            )
+        );
+        let remote = Repository::open_bare(remote_dir.path()).unwrap();
+        assert!(remote.find_reference(&format!("refs/heads/{}", branch)).is_err());
+
+        // --no-verify skips the hook
+        push(Some(&repo), &PushArgs { no_verify: true }).unwrap();
+        assert_eq!(remote.refname_to_id(&format!("refs/heads/{}", branch)).unwrap(), oid);
+    }
 }
@@ -0,0 +1,156 @@
+use std::collections::HashMap;
+use std::path::Path;
+use crate::types::staged::StagedChange;
+
+/// Returns the workspace members of the repository, as paths relative to
+/// the working directory.
+///
+/// Both Cargo workspaces (`[workspace] members` in `Cargo.toml`) and npm or
+/// yarn workspaces (`workspaces` in `package.json`) are recognised; trailing
+/// `/*` wildcards are expanded to the matching directories.
+pub fn workspace_members(workdir: &Path) -> Vec<String> {
+    let mut patterns: Vec<String> = Vec::new();
+
+    if let Ok(content) = std::fs::read_to_string(workdir.join("Cargo.toml"))
+        && let Ok(manifest) = content.parse::<toml::Table>()
+        && let Some(members) = manifest.get("workspace")
+            .and_then(|workspace| workspace.get("members"))
+            .and_then(|members| members.as_array()) {
+        patterns.extend(members.iter().filter_map(|m| m.as_str()).map(str::to_string));
+    }
+
+    if let Ok(content) = std::fs::read_to_string(workdir.join("package.json"))
+        && let Ok(package) = serde_json::from_str::<serde_json::Value>(&content) {
+        let workspaces = package.get("workspaces")
+            .map(|w| w.get("packages").unwrap_or(w))
+            .and_then(|w| w.as_array());
+        if let Some(workspaces) = workspaces {
+            patterns.extend(workspaces.iter().filter_map(|w| w.as_str()).map(str::to_string));
+        }
+    }
+
+    let mut members = Vec::new();
+    for pattern in patterns {
+        let pattern = pattern.trim_end_matches('/').to_string();
+        match pattern.strip_suffix("/*") {
+            Some(parent) => {
+                if let Ok(entries) = std::fs::read_dir(workdir.join(parent)) {
+                    let mut children: Vec<String> = entries.flatten()
+                        .filter(|entry| entry.path().is_dir())
+                        .map(|entry| format!("{}/{}", parent, entry.file_name().to_string_lossy()))
+                        .collect();
+                    children.sort();
+                    members.extend(children);
+                }
+            }
+            None => members.push(pattern),
+        }
+    }
+    members
+}
+
+/// Suggests commit scopes for a set of staged changes.
+///
+/// Every file inside a workspace member contributes the member name (its last
+/// path component); any other file contributes its top-level directory.
+/// Files at the repository root contribute nothing. Scopes are ranked by the
+/// number of staged files they cover, then alphabetically.
+pub fn suggest_scopes(changes: &[StagedChange], members: &[String]) -> Vec<String> {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+
+    for change in changes {
+        let paths = std::iter::once(&change.path).chain(change.old_path.as_ref());
+        for path in paths {
+            let member = members.iter()
+                .filter(|m| path.starts_with(&format!("{}/", m)))
+                .max_by_key(|m| m.len());
+            let scope = match member {
+                Some(member) => member.rsplit('/').next().map(str::to_string),
+                None => path.split_once('/').map(|(top, _)| top.to_string()),
+            };
+            if let Some(scope) = scope {
+                *counts.entry(scope).or_default() += 1;
+            }
+        }
+    }
+
+    let mut scopes: Vec<(String, usize)> = counts.into_iter().collect();
+    scopes.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    scopes.into_iter().map(|(scope, _)| scope).collect()
+}
+
+/// Builds the list of scopes offered by the scope picker.
+///
+/// Without an `allowed` list, the suggestions are offered as they are. With
+/// one, only allowed scopes are offered: the suggested ones first, then the
+/// remaining allowed scopes in their configured order.
+pub fn scope_options(suggestions: &[String], allowed: Option<&[String]>) -> Vec<String> {
+    match allowed {
+        None => suggestions.to_vec(),
+        Some(allowed) => {
+            let mut options: Vec<String> = suggestions.iter()
+                .filter(|s| allowed.contains(s))
+                .cloned()
+                .collect();
+            options.extend(allowed.iter().filter(|a| !options.contains(a)).cloned().collect::<Vec<String>>());
+            options
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use git2::Delta;
+    use tempfile::tempdir;
+
+    fn change(path: &str) -> StagedChange {
+        StagedChange { path: path.to_string(), old_path: None, status: Delta::Modified }
+    }
+
+    #[test]
+    fn test_suggest_scopes_from_directories() {
+        let changes = vec![change("src/main.rs"), change("docs/usage.md"), change("src/lib.rs"), change("README.md")];
+
+        assert_eq!(suggest_scopes(&changes, &[]), vec!["src".to_string(), "docs".to_string()]);
+    }
+
+    #[test]
+    fn test_suggest_scopes_from_members() {
+        let members = vec!["crates/parser".to_string(), "crates/cli".to_string()];
+        let changes = vec![
+            change("crates/parser/src/lib.rs"),
+            change("crates/cli/src/main.rs"),
+            change("crates/cli/Cargo.toml"),
+            change("tools/release.sh"),
+        ];
+
+        assert_eq!(suggest_scopes(&changes, &members), vec!["cli", "parser", "tools"]);
+    }
+
+    #[test]
+    fn test_scope_options() {
+        let suggestions = vec!["cli".to_string(), "docs".to_string()];
+        let allowed = vec!["parser".to_string(), "docs".to_string(), "ci".to_string()];
+
+        assert_eq!(scope_options(&suggestions, None), suggestions);
+        assert_eq!(scope_options(&suggestions, Some(&allowed)), vec!["docs", "parser", "ci"]);
+    }
+
+    #[test]
+    fn test_workspace_members() {
+        let temp_dir = tempdir().unwrap();
+        std::fs::write(
+            temp_dir.path().join("Cargo.toml"),
+            "[workspace]\nmembers = [\"crates/*\", \"xtask\"]\n",
+        ).unwrap();
+        std::fs::create_dir_all(temp_dir.path().join("crates/parser")).unwrap();
+        std::fs::create_dir_all(temp_dir.path().join("crates/cli")).unwrap();
+        std::fs::write(
+            temp_dir.path().join("package.json"),
+            "{\"workspaces\": {\"packages\": [\"web\"]}}",
+        ).unwrap();
+
+        assert_eq!(workspace_members(temp_dir.path()), vec!["crates/cli", "crates/parser", "xtask", "web"]);
+    }
+}
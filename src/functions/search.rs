@@ -0,0 +1,126 @@
+use crate::types::emoji::Emoji;
+
+// scores of the different kinds of match, best first
+const EXACT_SHORTCODE: i64 = 1000;
+const SHORTCODE_PREFIX: i64 = 800;
+const EXACT_KEYWORD: i64 = 700;
+const KEYWORD_PREFIX: i64 = 600;
+const SHORTCODE_SUBSTRING: i64 = 500;
+const DESCRIPTION_WORD: i64 = 400;
+const DESCRIPTION_SUBSTRING: i64 = 300;
+const FUZZY: i64 = 100;
+
+// scores `query` as a subsequence of `text`: compact matches score higher
+fn subsequence_score(query: &str, text: &str) -> Option<i64> {
+    let mut text_chars = text.chars().enumerate();
+    let mut first = None;
+    let mut last = 0;
+    for q in query.chars() {
+        let (position, _) = text_chars.find(|(_, c)| *c == q)?;
+        first.get_or_insert(position);
+        last = position;
+    }
+    let span = (last - first.unwrap_or(0) + 1) as i64;
+    let gaps = span - query.chars().count() as i64;
+    Some((FUZZY - gaps * 5).max(1))
+}
+
+// scores a single search term against an emoji
+fn term_score(term: &str, emoji: &Emoji) -> Option<i64> {
+    let shortcode = emoji.shortcode.to_lowercase();
+    let description = emoji.description.to_lowercase();
+    let keywords: Vec<String> = emoji.keywords.iter().map(|k| k.to_lowercase()).collect();
+
+    if shortcode == term || emoji.glyph == term {
+        return Some(EXACT_SHORTCODE);
+    }
+    if shortcode.starts_with(term) {
+        return Some(SHORTCODE_PREFIX);
+    }
+    if keywords.iter().any(|k| k == term) {
+        return Some(EXACT_KEYWORD);
+    }
+    if keywords.iter().any(|k| k.starts_with(term)) {
+        return Some(KEYWORD_PREFIX);
+    }
+    if shortcode.contains(term) {
+        return Some(SHORTCODE_SUBSTRING);
+    }
+    if description.split(|c: char| !c.is_alphanumeric()).any(|word| word.starts_with(term)) {
+        return Some(DESCRIPTION_WORD);
+    }
+    if description.contains(term) {
+        return Some(DESCRIPTION_SUBSTRING);
+    }
+    subsequence_score(term, &shortcode)
+}
+
+/// Scores how well an emoji matches a search query; `None` means no match.
+///
+/// The query is split on whitespace and every term must match the emoji's
+/// shortcode, keywords or description. Exact shortcode matches rank highest,
+/// then shortcode prefixes, keywords, substrings, description words and
+/// finally fuzzy (subsequence) matches on the shortcode. Colons around a
+/// shortcode are ignored and the comparison is case-insensitive. An empty
+/// query matches everything with a score of `0`.
+pub fn fuzzy_score(query: &str, emoji: &Emoji) -> Option<i64> {
+    let query = query.trim().to_lowercase();
+    query.split_whitespace()
+        .map(|term| term.trim_matches(':'))
+        .filter(|term| !term.is_empty())
+        .try_fold(0, |total, term| term_score(term, emoji).map(|score| total + score))
+}
+
+/// Returns the emojis matching `query`, best match first. Emojis with the
+/// same score keep their original order.
+pub fn search<'a>(emojis: &'a [Emoji], query: &str) -> Vec<&'a Emoji> {
+    let mut matches: Vec<(&Emoji, i64)> = emojis.iter()
+        .filter_map(|emoji| fuzzy_score(query, emoji).map(|score| (emoji, score)))
+        .collect();
+    matches.sort_by_key(|(_, score)| std::cmp::Reverse(*score));
+    matches.into_iter().map(|(emoji, _)| emoji).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::emoji::EmojiCatalog;
+
+    fn shortcodes(query: &str, count: usize) -> Vec<String> {
+        let catalog = EmojiCatalog::builtin();
+        let mut found: Vec<String> = search(catalog.emojis(), query).iter()
+            .take(count)
+            .map(|e| e.shortcode.clone())
+            .collect();
+        found.sort();
+        found
+    }
+
+    #[test]
+    fn test_keyword_aliases() {
+        assert_eq!(shortcodes("deps", 5), vec!["arrow_down", "arrow_up", "heavy_minus_sign", "heavy_plus_sign", "pushpin"]);
+        assert_eq!(shortcodes("test", 3), vec!["clown_face", "test_tube", "white_check_mark"]);
+    }
+
+    #[test]
+    fn test_exact_shortcode_ranks_first() {
+        let catalog = EmojiCatalog::builtin();
+
+        assert_eq!(search(catalog.emojis(), "bug")[0].shortcode, "bug");
+        assert_eq!(search(catalog.emojis(), ":lock:")[0].shortcode, "lock");
+        assert_eq!(search(catalog.emojis(), "Fire")[0].shortcode, "fire");
+    }
+
+    #[test]
+    fn test_fuzzy_and_multiple_terms() {
+        let catalog = EmojiCatalog::builtin();
+        let sparkles = catalog.find("sparkles").unwrap();
+
+        assert!(fuzzy_score("sprkl", sparkles).is_some());
+        assert!(fuzzy_score("sparkles", sparkles) > fuzzy_score("sprkl", sparkles));
+        assert!(fuzzy_score("new feature", sparkles).is_some());
+        assert!(fuzzy_score("new xyz", sparkles).is_none());
+        assert_eq!(fuzzy_score("", sparkles), Some(0));
+        assert_eq!(search(catalog.emojis(), "").len(), catalog.emojis().len());
+    }
+}
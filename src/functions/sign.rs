@@ -0,0 +1,364 @@
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use git2::{Error, ErrorClass, ErrorCode, Repository};
+use tempfile::TempDir;
+use crate::types::config::optional;
+
+/// The signature format, from `gpg.format`.
+///
+/// # Possible values
+///
+/// * `OpenPgp` - An OpenPGP signature made with `gpg` (the default).
+/// * `X509` - An X.509 (S/MIME) signature made with `gpgsm`.
+/// * `Ssh` - An SSH signature made with `ssh-keygen -Y sign`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SigningFormat {
+    OpenPgp,
+    X509,
+    Ssh,
+}
+
+impl std::str::FromStr for SigningFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "openpgp" => Ok(SigningFormat::OpenPgp),
+            "x509" => Ok(SigningFormat::X509),
+            "ssh" => Ok(SigningFormat::Ssh),
+            other => Err(Error::from_str(
+                format!("unknown gpg.format '{}', expected openpgp, x509 or ssh", other).as_str()
+            )),
+        }
+    }
+}
+
+impl SigningFormat {
+    // the `gpg.<format>.program` key, and the program used when it is unset
+    fn program(&self) -> (&'static str, &'static str) {
+        match self {
+            SigningFormat::OpenPgp => ("gpg.openpgp.program", "gpg"),
+            SigningFormat::X509 => ("gpg.x509.program", "gpgsm"),
+            SigningFormat::Ssh => ("gpg.ssh.program", "ssh-keygen"),
+        }
+    }
+}
+
+// the line gpg and gpgsm print on the status file descriptor once signed
+const SIG_CREATED: &str = "\n[GNUPG:] SIG_CREATED ";
+
+// an SSH public key given inline rather than as a file
+const SSH_KEY_PREFIX: &str = "key::";
+
+/// Signs commits like git does, with the configured external program.
+///
+/// # Fields
+///
+/// * `format` - The signature format.
+/// * `program` - The signing program.
+/// * `key` - The signing key: a key id for `gpg`/`gpgsm` (the committer
+///   identity when `user.signingkey` is unset), a key file or an inline
+///   public key for SSH.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Signer {
+    pub format: SigningFormat,
+    pub program: String,
+    pub key: String,
+}
+
+// a private directory (mode 0700, with a random name) in the temporary
+// directory, removed with its files when dropped; on a shared `/tmp`, no
+// other user can plant or read the files written there
+struct SigningDir(TempDir);
+
+impl SigningDir {
+    fn create() -> Result<Self, Error> {
+        let mut builder = tempfile::Builder::new();
+        builder.prefix("glyphit-");
+        #[cfg(unix)]
+        builder.permissions(std::os::unix::fs::PermissionsExt::from_mode(0o700));
+        builder.tempdir()
+            .map(Self)
+            .map_err(|e| Error::from_str(format!("cannot create a temporary directory: {}", e).as_str()))
+    }
+
+    fn path(&self, name: &str) -> PathBuf {
+        self.0.path().join(name)
+    }
+
+    // writes a new file (mode 0600), never following an existing one
+    fn write(&self, name: &str, content: &str) -> Result<PathBuf, Error> {
+        let path = self.path(name);
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        options.open(&path)
+            .and_then(|mut file| file.write_all(content.as_bytes()))
+            .map_err(|e| Error::from_str(format!("cannot write {}: {}", path.display(), e).as_str()))?;
+        Ok(path)
+    }
+}
+
+// expands a leading `~/` in a key path, like git
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), std::env::var_os("HOME")) {
+        (Some(rest), Some(home)) => PathBuf::from(home).join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+fn signing_error(program: &str, detail: &str) -> Error {
+    Error::new(ErrorCode::User, ErrorClass::Callback, format!(
+        "{} failed to sign the commit: {}", program, detail.trim()
+    ))
+}
+
+impl Signer {
+    /// Reads the signing configuration of a repository: `commit.gpgsign`,
+    /// `gpg.format`, `user.signingkey`, `gpg.program` and
+    /// `gpg.<format>.program`. Returns `None` when commits are not signed.
+    ///
+    /// `identity` (`Name <email>`) is the `gpg` key used when
+    /// `user.signingkey` is unset.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `Err(Error)` if the configuration cannot be read, a value is
+    /// invalid (`commit.gpgsign = sure`), the format is unknown, or SSH
+    /// signing has no `user.signingkey`.
+    pub fn from_config(repo: &Repository, identity: &str) -> Result<Option<Self>, Error> {
+        let config = repo.config()?;
+        // an unreadable value is an error: the commit must not go unsigned
+        if !optional(config.get_bool("commit.gpgsign"))?.unwrap_or(false) {
+            return Ok(None);
+        }
+
+        let format: SigningFormat = match optional(config.get_string("gpg.format"))? {
+            Some(format) => format.parse()?,
+            None => SigningFormat::OpenPgp,
+        };
+        let (program_key, default_program) = format.program();
+        let program = match (optional(config.get_string(program_key))?, format) {
+            (Some(program), _) => Some(program),
+            (None, SigningFormat::OpenPgp) => optional(config.get_string("gpg.program"))?,
+            (None, _) => None,
+        }.unwrap_or_else(|| default_program.to_string());
+        let key = match (optional(config.get_string("user.signingkey"))?, format) {
+            (Some(key), _) => key,
+            (None, SigningFormat::Ssh) => return Err(Error::new(
+                ErrorCode::NotFound, ErrorClass::Config, "user.signingkey must be set to sign commits with SSH"
+            )),
+            (None, _) => identity.to_string(),
+        };
+
+        Ok(Some(Self { format, program, key }))
+    }
+
+    /// Signs a commit buffer and returns the signature, to be stored in the
+    /// `gpgsig` header.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `Err(Error)` if the program cannot be run or fails to sign.
+    pub fn sign(&self, buffer: &str) -> Result<String, Error> {
+        match self.format {
+            SigningFormat::Ssh => self.sign_ssh(buffer),
+            _ => self.sign_gpg(buffer),
+        }
+    }
+
+    // `gpg --status-fd=2 -bsau <key>`, the buffer on stdin and the armored
+    // signature on stdout
+    fn sign_gpg(&self, buffer: &str) -> Result<String, Error> {
+        let mut child = Command::new(&self.program)
+            .args(["--status-fd=2", "-bsau", &self.key])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| signing_error(&self.program, &e.to_string()))?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(buffer.as_bytes()).map_err(|e| signing_error(&self.program, &e.to_string()))?;
+        }
+        let output = child.wait_with_output().map_err(|e| signing_error(&self.program, &e.to_string()))?;
+
+        let status = String::from_utf8_lossy(&output.stderr);
+        if !output.status.success() || !format!("\n{}", status).contains(SIG_CREATED) {
+            return Err(signing_error(&self.program, &status));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).replace("\r\n", "\n"))
+    }
+
+    // `ssh-keygen -Y sign -n git -f <key> <file>`, which writes `<file>.sig`;
+    // an inline public key is written to a file and used through the agent
+    fn sign_ssh(&self, buffer: &str) -> Result<String, Error> {
+        let dir = SigningDir::create()?;
+        let buffer_file = dir.write("commit", buffer)?;
+        let inline_key = self.key.strip_prefix(SSH_KEY_PREFIX)
+            .or_else(|| self.key.starts_with("ssh-").then_some(self.key.as_str()));
+        let key_file = match inline_key {
+            Some(key) => Some(dir.write("signing-key.pub", &format!("{}\n", key.trim()))?),
+            None => None,
+        };
+        let signature_file = dir.path("commit.sig");
+
+        let mut command = Command::new(&self.program);
+        command.args(["-Y", "sign", "-n", "git", "-f"]);
+        match &key_file {
+            Some(key_file) => command.arg(key_file).arg("-U"),
+            None => command.arg(expand_home(&self.key)),
+        };
+        let output = command.arg(&buffer_file)
+            .stdin(Stdio::null())
+            .output()
+            .map_err(|e| signing_error(&self.program, &e.to_string()))?;
+        if !output.status.success() {
+            return Err(signing_error(&self.program, &String::from_utf8_lossy(&output.stderr)));
+        }
+
+        std::fs::read_to_string(&signature_file)
+            .map_err(|e| signing_error(&self.program, &format!("cannot read the signature: {}", e)))
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    const BUFFER: &str = "tree 4b825dc642cb6eb9a060e54bf8d69288fbee4904\n\
+        author Test User <test@example.com> 0 +0000\n\
+        committer Test User <test@example.com> 0 +0000\n\n🎉 begin\n";
+
+    fn available(program: &str, arg: &str) -> bool {
+        Command::new(program).arg(arg).output().is_ok()
+    }
+
+    #[test]
+    fn test_from_config() {
+        let temp_dir = tempdir().unwrap();
+        let repo = Repository::init(temp_dir.path()).unwrap();
+        let identity = "Test User <test@example.com>";
+        assert_eq!(Signer::from_config(&repo, identity).unwrap(), None);
+
+        let mut config = repo.config().unwrap();
+        config.set_str("commit.gpgsign", "sure").unwrap();
+        assert_eq!(Signer::from_config(&repo, identity).unwrap_err().class(), ErrorClass::Config);
+        config.set_bool("commit.gpgsign", true).unwrap();
+        config.set_str("gpg.program", "gpg2").unwrap();
+        assert_eq!(Signer::from_config(&repo, identity).unwrap(), Some(Signer {
+            format: SigningFormat::OpenPgp,
+            program: "gpg2".to_string(),
+            key: identity.to_string(),
+        }));
+
+        config.set_str("gpg.format", "x509").unwrap();
+        assert_eq!(Signer::from_config(&repo, identity).unwrap().unwrap().program, "gpgsm");
+
+        config.set_str("gpg.format", "ssh").unwrap();
+        assert!(Signer::from_config(&repo, identity).is_err());
+        config.set_str("user.signingkey", "~/.ssh/id_ed25519.pub").unwrap();
+        assert_eq!(Signer::from_config(&repo, identity).unwrap(), Some(Signer {
+            format: SigningFormat::Ssh,
+            program: "ssh-keygen".to_string(),
+            key: "~/.ssh/id_ed25519.pub".to_string(),
+        }));
+
+        config.set_str("gpg.format", "pkcs7").unwrap();
+        assert!(Signer::from_config(&repo, identity).is_err());
+    }
+
+    #[test]
+    fn test_signing_dir() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = SigningDir::create().unwrap();
+        let root = dir.0.path().to_path_buf();
+        assert_eq!(std::fs::metadata(&root).unwrap().permissions().mode() & 0o777, 0o700);
+
+        let file = dir.write("commit", "content").unwrap();
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "content");
+        assert_eq!(std::fs::metadata(&file).unwrap().permissions().mode() & 0o777, 0o600);
+        // an existing file, or a symlink planted in its place, is not written through
+        assert!(dir.write("commit", "forged").is_err());
+        std::os::unix::fs::symlink(&file, dir.path("link")).unwrap();
+        assert!(dir.write("link", "forged").is_err());
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "content");
+
+        drop(dir);
+        assert!(!root.exists());
+    }
+
+    #[test]
+    fn test_sign_ssh() {
+        if !available("ssh-keygen", "-?") {
+            return;
+        }
+        let temp_dir = tempdir().unwrap();
+        let key = temp_dir.path().join("key");
+        let status = Command::new("ssh-keygen")
+            .args(["-q", "-t", "ed25519", "-N", "", "-C", "test@example.com", "-f"])
+            .arg(&key)
+            .status()
+            .unwrap();
+        assert!(status.success());
+
+        let signer = Signer {
+            format: SigningFormat::Ssh,
+            program: "ssh-keygen".to_string(),
+            key: key.to_string_lossy().to_string(),
+        };
+        let signature = signer.sign(BUFFER).unwrap();
+        assert!(signature.starts_with("-----BEGIN SSH SIGNATURE-----"));
+
+        let signature_file = temp_dir.path().join("commit.sig");
+        std::fs::write(&signature_file, &signature).unwrap();
+        let mut check = Command::new("ssh-keygen")
+            .args(["-Y", "check-novalidate", "-n", "git", "-s"])
+            .arg(&signature_file)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .spawn()
+            .unwrap();
+        check.stdin.take().unwrap().write_all(BUFFER.as_bytes()).unwrap();
+        assert!(check.wait().unwrap().success());
+
+        let missing = Signer { key: temp_dir.path().join("missing").to_string_lossy().to_string(), ..signer };
+        assert!(missing.sign(BUFFER).is_err());
+    }
+
+    #[test]
+    fn test_sign_gpg() {
+        if !available("gpg", "--version") {
+            return;
+        }
+        let home = tempdir().unwrap();
+        let generated = Command::new("gpg")
+            .arg("--homedir").arg(home.path())
+            .args(["--batch", "--passphrase", "", "--quick-generate-key", "Test User <test@example.com>", "ed25519", "sign", "never"])
+            .output()
+            .unwrap();
+        assert!(generated.status.success(), "{}", String::from_utf8_lossy(&generated.stderr));
+
+        // a wrapper keeps the throwaway keyring out of the user's
+        let program = home.path().join("gpg-test");
+        std::fs::write(&program, format!("#!/bin/sh\nexec gpg --homedir '{}' \"$@\"\n", home.path().display())).unwrap();
+        std::fs::set_permissions(&program, std::os::unix::fs::PermissionsExt::from_mode(0o755)).unwrap();
+
+        let signer = Signer {
+            format: SigningFormat::OpenPgp,
+            program: program.to_string_lossy().to_string(),
+            key: "Test User <test@example.com>".to_string(),
+        };
+        let signature = signer.sign(BUFFER).unwrap();
+        assert!(signature.starts_with("-----BEGIN PGP SIGNATURE-----"));
+
+        let unknown = Signer { key: "nobody@example.com".to_string(), ..signer };
+        assert!(unknown.sign(BUFFER).is_err());
+
+        let _ = Command::new("gpgconf").arg("--homedir").arg(home.path()).args(["--kill", "gpg-agent"]).status();
+    }
+}
@@ -0,0 +1,356 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use git2::{Delta, Error, Repository};
+use regex::Regex;
+use crate::types::config::SuggestionRule;
+use crate::types::emoji::Emoji;
+use crate::types::staged::{head_tree, staged_changes, StagedChange};
+
+/// A dependency change found in a staged `Cargo.toml`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DependencyChange {
+    Added,
+    Removed,
+    Upgraded,
+    Downgraded,
+}
+
+// scores used to rank the suggestions
+const ONLY_SCORE: u32 = 100;
+const CUSTOM_ONLY_SCORE: u32 = 110;
+const DEPENDENCY_SCORE: u32 = 90;
+const CUSTOM_ANY_SCORE: u32 = 60;
+const ANY_SCORE: u32 = 50;
+
+fn is_markdown(path: &str) -> bool {
+    path.to_lowercase().ends_with(".md")
+}
+
+fn is_test(path: &str) -> bool {
+    let file_name = path.rsplit('/').next().unwrap_or(path);
+    let stem = file_name.split('.').next().unwrap_or(file_name);
+    path.split('/').any(|dir| dir == "tests" || dir == "test" || dir == "__tests__")
+        || stem.starts_with("test_")
+        || stem.ends_with("_test")
+        || stem.ends_with("_spec")
+        || file_name.contains(".test.")
+        || file_name.contains(".spec.")
+}
+
+fn is_gitignore(path: &str) -> bool {
+    path.rsplit('/').next() == Some(".gitignore")
+}
+
+fn is_ci(path: &str) -> bool {
+    let yaml = path.ends_with(".yml") || path.ends_with(".yaml");
+    (yaml && (path.starts_with(".github/workflows/") || path.starts_with(".circleci/")))
+        || path == ".gitlab-ci.yml"
+        || path == "azure-pipelines.yml"
+        || path == ".travis.yml"
+}
+
+/// Ranks the shortcodes of the emojis that best describe a set of staged
+/// changes, most likely first.
+///
+/// The built-in heuristics are:
+///
+/// * only deletions → `fire`, renames → `truck`;
+/// * only Markdown files → `memo`, only test files → `white_check_mark`;
+/// * `Cargo.toml` dependencies added, removed, upgraded or downgraded →
+///   `heavy_plus_sign`, `heavy_minus_sign`, `arrow_up`, `arrow_down`;
+/// * `.gitignore` → `see_no_evil`, CI YAML → `construction_worker`.
+///
+/// Heuristics requiring *every* file to match rank above the ones matching a
+/// single file. Custom `rules` rank above the built-in ones of the same kind.
+///
+/// # Errors
+///
+/// Returns an `Err(Error)` if a custom rule has an invalid pattern.
+pub fn suggest_emojis(
+    changes: &[StagedChange],
+    dependencies: &[DependencyChange],
+    rules: &[SuggestionRule],
+) -> Result<Vec<String>, Error> {
+    let mut scores: Vec<(String, u32)> = Vec::new();
+    let mut score = |shortcode: &str, value: u32| {
+        match scores.iter_mut().find(|(s, _)| s == shortcode) {
+            Some(entry) => entry.1 = entry.1.max(value),
+            None => scores.push((shortcode.to_string(), value)),
+        }
+    };
+
+    if changes.is_empty() {
+        return Ok(Vec::new());
+    }
+    let paths: Vec<&str> = changes.iter().map(|c| c.path.as_str()).collect();
+    let all = |predicate: fn(&str) -> bool| paths.iter().all(|p| predicate(p));
+    let any = |predicate: fn(&str) -> bool| paths.iter().any(|p| predicate(p));
+
+    for rule in rules {
+        let regex = Regex::new(&rule.pattern)
+            .map_err(|e| Error::from_str(format!("invalid suggestion pattern '{}': {}", rule.pattern, e).as_str()))?;
+        let matches = |p: &&str| regex.is_match(p);
+        if rule.only && paths.iter().all(matches) {
+            score(&rule.emoji, CUSTOM_ONLY_SCORE);
+        } else if !rule.only && paths.iter().any(matches) {
+            score(&rule.emoji, CUSTOM_ANY_SCORE);
+        }
+    }
+
+    if changes.iter().all(|c| c.status == Delta::Deleted) {
+        score("fire", ONLY_SCORE);
+    }
+    if changes.iter().all(|c| c.status == Delta::Renamed) {
+        score("truck", ONLY_SCORE);
+    } else if changes.iter().any(|c| c.status == Delta::Renamed) {
+        score("truck", ANY_SCORE);
+    }
+    if all(is_markdown) {
+        score("memo", ONLY_SCORE);
+    }
+    if all(is_test) {
+        score("white_check_mark", ONLY_SCORE);
+    }
+    if all(is_gitignore) {
+        score("see_no_evil", ONLY_SCORE);
+    } else if any(is_gitignore) {
+        score("see_no_evil", ANY_SCORE);
+    }
+    if all(is_ci) {
+        score("construction_worker", ONLY_SCORE);
+    } else if any(is_ci) {
+        score("construction_worker", ANY_SCORE);
+    }
+    for dependency in dependencies {
+        let shortcode = match dependency {
+            DependencyChange::Added => "heavy_plus_sign",
+            DependencyChange::Removed => "heavy_minus_sign",
+            DependencyChange::Upgraded => "arrow_up",
+            DependencyChange::Downgraded => "arrow_down",
+        };
+        score(shortcode, DEPENDENCY_SCORE);
+    }
+
+    // stable sort: equal scores keep their rule order
+    scores.sort_by_key(|(_, value)| std::cmp::Reverse(*value));
+    Ok(scores.into_iter().map(|(shortcode, _)| shortcode).collect())
+}
+
+// collects `name -> version` from every dependency table of a manifest
+fn dependency_versions(manifest: &str) -> BTreeMap<String, String> {
+    let mut versions = BTreeMap::new();
+    let table = match manifest.parse::<toml::Table>() {
+        Ok(table) => table,
+        Err(_) => return versions,
+    };
+
+    let mut tables: Vec<&toml::Table> = vec![&table];
+    if let Some(workspace) = table.get("workspace").and_then(|w| w.as_table()) {
+        tables.push(workspace);
+    }
+    if let Some(targets) = table.get("target").and_then(|t| t.as_table()) {
+        tables.extend(targets.values().filter_map(|t| t.as_table()));
+    }
+
+    for section in tables {
+        for kind in ["dependencies", "dev-dependencies", "build-dependencies"] {
+            let dependencies = match section.get(kind).and_then(|d| d.as_table()) {
+                Some(dependencies) => dependencies,
+                None => continue,
+            };
+            for (name, spec) in dependencies {
+                let version = match spec {
+                    toml::Value::String(version) => version.clone(),
+                    toml::Value::Table(spec) => spec.get("version")
+                        .and_then(|v| v.as_str())
+                        .unwrap_or_default()
+                        .to_string(),
+                    _ => String::new(),
+                };
+                versions.insert(format!("{}/{}", kind, name), version);
+            }
+        }
+    }
+    versions
+}
+
+// numeric components of a version requirement, e.g. "^1.2" -> [1, 2]
+fn version_components(version: &str) -> Vec<u64> {
+    version.trim_start_matches(|c: char| !c.is_ascii_digit())
+        .split(['.', '-', '+'])
+        .map_while(|part| part.parse().ok())
+        .collect()
+}
+
+/// Compares the dependency tables of two versions of a `Cargo.toml`.
+pub fn dependency_changes(old: &str, new: &str) -> Vec<DependencyChange> {
+    let old_versions = dependency_versions(old);
+    let new_versions = dependency_versions(new);
+    let mut changes = Vec::new();
+
+    for (name, new_version) in &new_versions {
+        match old_versions.get(name) {
+            None => changes.push(DependencyChange::Added),
+            Some(old_version) => {
+                match version_components(new_version).cmp(&version_components(old_version)) {
+                    std::cmp::Ordering::Greater => changes.push(DependencyChange::Upgraded),
+                    std::cmp::Ordering::Less => changes.push(DependencyChange::Downgraded),
+                    std::cmp::Ordering::Equal => {}
+                }
+            }
+        }
+    }
+    for name in old_versions.keys() {
+        if !new_versions.contains_key(name) {
+            changes.push(DependencyChange::Removed);
+        }
+    }
+    changes
+}
+
+// reads the staged `Cargo.toml` files and compares them with `HEAD`
+fn staged_dependency_changes(repo: &Repository, changes: &[StagedChange]) -> Result<Vec<DependencyChange>, Error> {
+    let tree = head_tree(repo)?;
+    let index = repo.index()?;
+    let blob_content = |oid| -> Result<String, Error> {
+        Ok(String::from_utf8_lossy(repo.find_blob(oid)?.content()).to_string())
+    };
+
+    let mut dependencies = Vec::new();
+    for change in changes.iter().filter(|c| c.path.rsplit('/').next() == Some("Cargo.toml")) {
+        let old = match &tree {
+            Some(tree) => match tree.get_path(Path::new(change.old_path.as_ref().unwrap_or(&change.path))) {
+                Ok(entry) => blob_content(entry.id())?,
+                Err(_) => String::new(),
+            },
+            None => String::new(),
+        };
+        let new = match index.get_path(Path::new(&change.path), 0) {
+            Some(entry) if change.status != Delta::Deleted => blob_content(entry.id)?,
+            _ => String::new(),
+        };
+        dependencies.extend(dependency_changes(&old, &new));
+    }
+    Ok(dependencies)
+}
+
+/// Suggests emojis for the changes currently staged in `repo`.
+///
+/// # Errors
+///
+/// Returns an `Err(Error)` if the staged changes cannot be read or a custom
+/// rule has an invalid pattern.
+pub fn suggest_for_repository(repo: &Repository, rules: &[SuggestionRule]) -> Result<Vec<String>, Error> {
+    let changes = staged_changes(repo)?;
+    let dependencies = staged_dependency_changes(repo, &changes)?;
+    suggest_emojis(&changes, &dependencies, rules)
+}
+
+/// Moves the suggested emojis, in order, to the top of the list.
+pub fn rank_first(emojis: &[Emoji], preferred: &[String]) -> Vec<Emoji> {
+    let mut ranked: Vec<Emoji> = preferred.iter()
+        .filter_map(|p| emojis.iter().find(|e| &e.shortcode == p || &e.glyph == p))
+        .cloned()
+        .collect();
+    ranked.dedup();
+    let rest: Vec<Emoji> = emojis.iter().filter(|e| !ranked.contains(e)).cloned().collect();
+    ranked.extend(rest);
+    ranked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use git2::Signature;
+    use tempfile::tempdir;
+    use crate::functions::add::add;
+    use crate::types::emoji::EmojiCatalog;
+
+    fn change(path: &str, status: Delta) -> StagedChange {
+        StagedChange { path: path.to_string(), old_path: None, status }
+    }
+
+    fn suggest(changes: &[StagedChange]) -> Vec<String> {
+        suggest_emojis(changes, &[], &[]).unwrap()
+    }
+
+    #[test]
+    fn test_builtin_heuristics() {
+        assert!(suggest(&[]).is_empty());
+        assert_eq!(suggest(&[change("README.md", Delta::Modified), change("docs/a.md", Delta::Added)]), vec!["memo"]);
+        assert_eq!(suggest(&[change("tests/cli.rs", Delta::Modified), change("src/foo_test.go", Delta::Added)]), vec!["white_check_mark"]);
+        assert_eq!(suggest(&[change("src/old.rs", Delta::Deleted)]), vec!["fire"]);
+        assert_eq!(suggest(&[change("src/new.rs", Delta::Renamed)]), vec!["truck"]);
+        assert_eq!(suggest(&[change(".gitignore", Delta::Modified)]), vec!["see_no_evil"]);
+        assert_eq!(suggest(&[change(".github/workflows/rust.yml", Delta::Modified)]), vec!["construction_worker"]);
+        assert_eq!(
+            suggest(&[change(".github/workflows/rust.yml", Delta::Modified), change("src/main.rs", Delta::Modified)]),
+            vec!["construction_worker"]
+        );
+        assert!(suggest(&[change("src/main.rs", Delta::Modified)]).is_empty());
+    }
+
+    #[test]
+    fn test_custom_rules() {
+        let rules = vec![
+            SuggestionRule { pattern: "^migrations/".to_string(), emoji: "card_file_box".to_string(), only: true },
+            SuggestionRule { pattern: "\\.css$".to_string(), emoji: "lipstick".to_string(), only: false },
+        ];
+        let changes = vec![change("migrations/001.sql", Delta::Added), change("migrations/002.sql", Delta::Deleted)];
+        assert_eq!(suggest_emojis(&changes, &[], &rules).unwrap(), vec!["card_file_box"]);
+
+        let changes = vec![change("web/app.css", Delta::Modified), change("web/app.js", Delta::Modified)];
+        assert_eq!(suggest_emojis(&changes, &[], &rules).unwrap(), vec!["lipstick"]);
+
+        let invalid = vec![SuggestionRule { pattern: "(".to_string(), emoji: "bug".to_string(), only: false }];
+        assert!(suggest_emojis(&changes, &[], &invalid).is_err());
+    }
+
+    #[test]
+    fn test_dependency_changes() {
+        let old = "[dependencies]\nserde = \"1.0\"\nregex = { version = \"1.5\" }\nlog = \"0.4\"\n";
+        let new = "[dependencies]\nserde = \"1.1\"\nregex = { version = \"1.4\" }\n\n[dev-dependencies]\ntempfile = \"3\"\n";
+
+        let mut changes = dependency_changes(old, new);
+        changes.sort_by_key(|c| *c as u8);
+
+        assert_eq!(changes, vec![
+            DependencyChange::Added,
+            DependencyChange::Removed,
+            DependencyChange::Upgraded,
+            DependencyChange::Downgraded,
+        ]);
+        assert_eq!(
+            suggest_emojis(&[change("Cargo.toml", Delta::Modified)], &[DependencyChange::Upgraded], &[]).unwrap(),
+            vec!["arrow_up"]
+        );
+    }
+
+    #[test]
+    fn test_suggest_for_repository() {
+        let temp_dir = tempdir().unwrap();
+        let repo = Repository::init(temp_dir.path()).unwrap();
+
+        std::fs::write(temp_dir.path().join("Cargo.toml"), "[dependencies]\ngit2 = \"0.19\"\n").unwrap();
+        add(&["Cargo.toml".to_string()], Some(&repo)).unwrap();
+        let signature = Signature::now("Test User", "test@example.com").unwrap();
+        let tree = repo.find_tree(repo.index().unwrap().write_tree().unwrap()).unwrap();
+        repo.commit(Some("HEAD"), &signature, &signature, "Initial commit", &tree, &[]).unwrap();
+
+        std::fs::write(temp_dir.path().join("Cargo.toml"), "[dependencies]\ngit2 = \"0.20\"\n").unwrap();
+        add(&["Cargo.toml".to_string()], Some(&repo)).unwrap();
+
+        assert_eq!(suggest_for_repository(&repo, &[]).unwrap(), vec!["arrow_up"]);
+    }
+
+    #[test]
+    fn test_rank_first() {
+        let catalog = EmojiCatalog::builtin();
+        let ranked = rank_first(catalog.emojis(), &["memo".to_string(), "bug".to_string()]);
+
+        assert_eq!(ranked.len(), catalog.emojis().len());
+        assert_eq!(ranked[0].shortcode, "memo");
+        assert_eq!(ranked[1].shortcode, "bug");
+        assert_eq!(ranked[2].shortcode, "art");
+    }
+}
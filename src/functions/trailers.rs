@@ -0,0 +1,215 @@
+use std::collections::HashMap;
+use git2::{Error, ErrorClass, ErrorCode, Repository, Signature, Sort};
+use inquire::autocompletion::Replacement;
+use inquire::{Autocomplete, CustomUserError};
+use regex::Regex;
+
+/// Trailer tokens written by `glyphit commit`.
+pub const SIGNED_OFF_BY: &str = "Signed-off-by";
+pub const CO_AUTHORED_BY: &str = "Co-authored-by";
+pub use crate::types::template::{CLOSES, REFS};
+
+// how many commits are searched for previous authors
+const AUTHOR_HISTORY: usize = 500;
+
+// a complete `Name <email>` identity
+const IDENTITY_PATTERN: &str = r"^[^<>]+ <[^<>\s]+@[^<>\s]+>$";
+
+// how many suggestions the co-author prompt shows
+const MAX_SUGGESTIONS: usize = 8;
+
+fn identity(signature: &Signature) -> Option<String> {
+    Some(format!("{} <{}>", signature.name()?, signature.email()?))
+}
+
+/// Returns the identities (`Name <email>`) that authored, committed or
+/// co-authored the recent commits of the current branch, most frequent
+/// first. `exclude` (the user's own identity) is left out.
+///
+/// # Errors
+///
+/// Returns an `Err(Error)` if the history cannot be walked.
+pub fn known_authors(repo: &Repository, exclude: &str) -> Result<Vec<String>, Error> {
+    if repo.head().is_err() {
+        return Ok(Vec::new());
+    }
+    let mut revwalk = repo.revwalk()?;
+    revwalk.push_head()?;
+    revwalk.set_sorting(Sort::TIME)?;
+
+    let co_author = format!("{}:", CO_AUTHORED_BY.to_lowercase());
+    // identity -> (count, rank of the first, i.e. latest, appearance)
+    let mut seen: HashMap<String, (usize, usize)> = HashMap::new();
+    for oid in revwalk.take(AUTHOR_HISTORY) {
+        let commit = repo.find_commit(oid?)?;
+        let message = String::from_utf8_lossy(commit.message_bytes()).to_string();
+        let mut identities: Vec<String> = [identity(&commit.author()), identity(&commit.committer())]
+            .into_iter()
+            .flatten()
+            .collect();
+        identities.extend(message.lines()
+            .filter(|line| line.to_lowercase().starts_with(&co_author))
+            .map(|line| line[co_author.len()..].trim().to_string()));
+        identities.sort();
+        identities.dedup();
+
+        for identity in identities {
+            let rank = seen.len();
+            seen.entry(identity).or_insert((0, rank)).0 += 1;
+        }
+    }
+
+    let mut authors: Vec<(String, (usize, usize))> = seen.into_iter()
+        .filter(|(identity, _)| !identity.eq_ignore_ascii_case(exclude))
+        .collect();
+    authors.sort_by(|(_, (count_a, rank_a)), (_, (count_b, rank_b))| count_b.cmp(count_a).then(rank_a.cmp(rank_b)));
+    Ok(authors.into_iter().map(|(identity, _)| identity).collect())
+}
+
+// the known authors containing the query, ignoring case
+fn matching_authors<'a>(query: &str, known: &'a [String]) -> Vec<&'a String> {
+    let query = query.trim().to_lowercase();
+    known.iter().filter(|author| author.to_lowercase().contains(&query)).collect()
+}
+
+/// Returns the query, trimmed, when it is a complete `Name <email>` identity
+/// that needs no lookup in the known authors.
+pub fn full_identity(query: &str) -> Option<String> {
+    let query = query.trim();
+    Regex::new(IDENTITY_PATTERN).expect("valid identity pattern").is_match(query).then(|| query.to_string())
+}
+
+/// Resolves an author or co-author: a complete `Name <email>` identity is
+/// used as it is, anything else is looked up (case-insensitively) in the
+/// `known` authors, and must match exactly one of them.
+///
+/// # Errors
+///
+/// Returns an `Err(Error)` if the query matches no known author, or several.
+pub fn resolve_author(query: &str, known: &[String]) -> Result<String, Error> {
+    if let Some(identity) = full_identity(query) {
+        return Ok(identity);
+    }
+    let query = query.trim();
+
+    match matching_authors(query, known).as_slice() {
+        [] => Err(Error::new(ErrorCode::NotFound, ErrorClass::Invalid, format!(
+            "unknown author '{}', expected 'Name <email>' or part of a previous author", query
+        ))),
+        [author] => Ok(author.to_string()),
+        authors => Err(Error::new(ErrorCode::Ambiguous, ErrorClass::Invalid, format!(
+            "author '{}' is ambiguous, it matches: {}",
+            query, authors.iter().map(|a| a.as_str()).collect::<Vec<&str>>().join(", ")
+        ))),
+    }
+}
+
+/// Splits a `Name <email>` identity into its name and email.
+pub fn split_identity(identity: &str) -> Option<(String, String)> {
+    let (name, rest) = identity.trim().split_once('<')?;
+    let email = rest.strip_suffix('>')?;
+    Some((name.trim().to_string(), email.trim().to_string()))
+}
+
+/// Returns an issue reference as written in a trailer: a bare issue number
+/// gets a `#` (`123` is `#123`), other references (`PROJ-7`) are kept.
+pub fn issue_reference(issue: &str) -> String {
+    let issue = issue.trim();
+    match !issue.is_empty() && issue.chars().all(|c| c.is_ascii_digit()) {
+        true => format!("#{}", issue),
+        false => issue.to_string(),
+    }
+}
+
+/// Autocompletion of the co-author prompt, from the known authors.
+#[derive(Debug, Clone)]
+pub struct AuthorCompleter {
+    authors: Vec<String>,
+}
+
+impl AuthorCompleter {
+    /// Creates a completer suggesting the given identities.
+    pub fn new(authors: Vec<String>) -> Self {
+        Self { authors }
+    }
+}
+
+impl Autocomplete for AuthorCompleter {
+    fn get_suggestions(&mut self, input: &str) -> Result<Vec<String>, CustomUserError> {
+        if input.trim().is_empty() {
+            return Ok(Vec::new());
+        }
+        Ok(matching_authors(input, &self.authors).into_iter()
+            .take(MAX_SUGGESTIONS)
+            .cloned()
+            .collect())
+    }
+
+    fn get_completion(&mut self, input: &str, highlighted: Option<String>) -> Result<Replacement, CustomUserError> {
+        Ok(highlighted.or_else(|| matching_authors(input, &self.authors).first().map(|a| a.to_string())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_known_authors() {
+        let temp_dir = tempdir().unwrap();
+        let repo = Repository::init(temp_dir.path()).unwrap();
+        assert!(known_authors(&repo, "").unwrap().is_empty());
+
+        let tree = repo.find_tree(repo.index().unwrap().write_tree().unwrap()).unwrap();
+        let alice = Signature::now("Alice", "alice@example.com").unwrap();
+        let bob = Signature::now("Bob", "bob@example.com").unwrap();
+        let first = repo.commit(Some("HEAD"), &alice, &alice, "🎉 begin\n", &tree, &[]).unwrap();
+        let first = repo.find_commit(first).unwrap();
+        let message = "✨ pair\n\nCo-authored-by: Carol <carol@example.com>\n";
+        repo.commit(Some("HEAD"), &bob, &alice, message, &tree, &[&first]).unwrap();
+
+        assert_eq!(
+            known_authors(&repo, "Bob <bob@example.com>").unwrap(),
+            vec!["Alice <alice@example.com>", "Carol <carol@example.com>"]
+        );
+    }
+
+    #[test]
+    fn test_resolve_author() {
+        let known = vec!["Alice Smith <alice@example.com>".to_string(), "Alan Turing <alan@example.com>".to_string()];
+
+        assert_eq!(resolve_author("smith", &known).unwrap(), "Alice Smith <alice@example.com>");
+        assert_eq!(resolve_author("ALAN@", &known).unwrap(), "Alan Turing <alan@example.com>");
+        assert_eq!(resolve_author(" Dan <dan@example.com> ", &known).unwrap(), "Dan <dan@example.com>");
+        assert_eq!(resolve_author("al", &known).unwrap_err().code(), ErrorCode::Ambiguous);
+        assert_eq!(resolve_author("dan", &known).unwrap_err().code(), ErrorCode::NotFound);
+
+        let mut completer = AuthorCompleter::new(known);
+        assert_eq!(completer.get_suggestions("al").unwrap().len(), 2);
+        assert_eq!(completer.get_completion("turing", None).unwrap().as_deref(), Some("Alan Turing <alan@example.com>"));
+    }
+
+    #[test]
+    fn test_full_identity() {
+        assert_eq!(full_identity(" Dan <dan@example.com> ").as_deref(), Some("Dan <dan@example.com>"));
+        assert_eq!(full_identity("dan"), None);
+        assert_eq!(full_identity("<dan@example.com>"), None);
+    }
+
+    #[test]
+    fn test_split_identity() {
+        assert_eq!(
+            split_identity(" Alice Smith <alice@example.com>"),
+            Some(("Alice Smith".to_string(), "alice@example.com".to_string()))
+        );
+        assert_eq!(split_identity("alice@example.com"), None);
+    }
+
+    #[test]
+    fn test_issue_reference() {
+        assert_eq!(issue_reference("123"), "#123");
+        assert_eq!(issue_reference("#123"), "#123");
+        assert_eq!(issue_reference("PROJ-7"), "PROJ-7");
+    }
+}
@@ -4,21 +4,26 @@ mod functions;
 use clap::Parser;
 use types::commands::{Cli, Command};
 use functions::add::add;
+use functions::check::check;
 use functions::commit::commit;
+use functions::hooks::hooks;
+use functions::lint::lint;
 use functions::push::push;
 
 fn main() {
     let cli = Cli::parse();
 
-    match &cli.command {
-        Command::Add { files } => {
-            let _ = add(files, None);
-        }
-        Command::Commit => {
-            let _ = commit(None, false);
-        }
-        Command::Push => {
-            let _ = push(None);
-        }
+    let result = match &cli.command {
+        Command::Add { files } => add(files, None),
+        Command::Commit(args) => commit(None, args).map(|_| ()),
+        Command::Push(args) => push(None, args),
+        Command::Lint { file } => lint(None, file.as_deref()),
+        Command::Check { range, format } => check(None, range, *format),
+        Command::Hooks { action } => hooks(None, action),
+    };
+
+    if let Err(e) = result {
+        eprintln!("error: {}", e.message());
+        std::process::exit(1);
     }
 }
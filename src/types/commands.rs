@@ -1,4 +1,5 @@
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use crate::types::emoji::EmojiFormat;
 
 /// Represents the available subcommands for the CLI application.
 ///
@@ -8,20 +9,202 @@ use clap::{Parser, Subcommand};
 /// # Possible values
 ///
 /// * `Add` - Adds one or more files to the staging area.
-///           Contains a single field:
+///   Contains a single field:
 ///   - `files` - A vector of file paths (`Vec<String>`) to add.
 ///
 /// * `Commit` - Creates a new commit with a message enriched with emoji.
+///   Every part of the message can be supplied through [`CommitArgs`].
 ///
 /// * `Push` - Pushes the current branch to the remote repository.
+///   Options are described in [`PushArgs`].
+///
+/// * `Lint` - Checks a commit message against the configured rules.
+///   Contains a single field:
+///   - `file` - The message file to check; the standard input when missing
+///     or `-`.
+///
+/// * `Check` - Lints every commit message of a revision range.
+///   Contains two fields:
+///   - `range` - The revision range, e.g. `origin/main..HEAD`.
+///   - `format` - The report format (`text`, `json` or `junit`).
+///
+/// * `Hooks` - Manages the glyphit git hooks, see [`HooksAction`].
 ///
 #[derive(Subcommand, Debug)]
 pub(crate) enum Command {
     Add {
         files: Vec<String>
     },
-    Commit,
-    Push
+    Commit(Box<CommitArgs>),
+    Push(PushArgs),
+    Lint {
+        file: Option<String>
+    },
+    Check {
+        range: String,
+        #[arg(long, value_enum, default_value_t = ReportFormat::Text)]
+        format: ReportFormat,
+    },
+    Hooks {
+        #[command(subcommand)]
+        action: HooksAction,
+    },
+}
+
+/// Actions of the `hooks` subcommand.
+///
+/// # Possible values
+///
+/// * `Install` - Installs the `commit-msg` hook, which lints messages, and
+///   with `--prepare-commit-msg` the hook adding an emoji to plain
+///   `git commit` messages. `--force` moves foreign hooks aside and chains
+///   to them.
+/// * `Uninstall` - Removes the glyphit hooks and restores chained ones.
+/// * `Status` - Shows which hooks are installed.
+/// * `PrepareCommitMsg` - Runs the `prepare-commit-msg` hook (hidden; called
+///   by the installed hook with git's arguments).
+#[derive(Subcommand, Debug)]
+pub(crate) enum HooksAction {
+    Install {
+        #[arg(long)]
+        prepare_commit_msg: bool,
+        #[arg(long)]
+        force: bool,
+    },
+    Uninstall,
+    Status,
+    #[command(hide = true)]
+    PrepareCommitMsg {
+        file: String,
+        source: Option<String>,
+        sha: Option<String>,
+    },
+}
+
+/// Output format of the `check` subcommand.
+///
+/// # Possible values
+///
+/// * `Text` - A human readable report.
+/// * `Json` - A JSON document, for scripts.
+/// * `Junit` - A JUnit XML test suite, for CI test reports.
+#[derive(ValueEnum, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ReportFormat {
+    #[default]
+    Text,
+    Json,
+    Junit,
+}
+
+/// Arguments accepted by the `commit` subcommand.
+///
+/// Every value that is not supplied on the command line is prompted for,
+/// unless `--no-interactive` is given, in which case a missing emoji or
+/// title is reported as an error.
+///
+/// # Fields
+///
+/// * `emoji` - The emoji to prefix the message with, either as a glyph
+///   (`✨`) or as a shortcode (`sparkles` or `:sparkles:`).
+/// * `scope` - The scope of the change, used by templates with a `{scope}`
+///   placeholder. Prompted for (and optional) when missing.
+/// * `title` - The commit title (subject line).
+/// * `body` - The commit body.
+/// * `breaking` - A description of the breaking changes, if any.
+/// * `emoji_format` - How the emoji is rendered in the subject, overriding
+///   the `glyphit.emojiFormat` git configuration.
+/// * `no_interactive` - Never prompt; fail when a required value is missing.
+/// * `no_verify` - Skip the `pre-commit` and `commit-msg` hooks, like
+///   `git commit --no-verify`.
+/// * `edit` - Open the composed message in the editor (`core.editor`,
+///   `$VISUAL`, `$EDITOR`) before committing; the body and breaking changes
+///   are then written there instead of being prompted for.
+/// * `amend` - Replace the `HEAD` commit, keeping its parents and author.
+///   The parts of its message pre-fill the prompts, and are kept as they are
+///   in non-interactive mode unless overridden by flags.
+/// * `fixup` - Write a `fixup! <subject>` commit for the given revision, which
+///   `git rebase --autosquash` folds into it. Without a revision, the commit
+///   is picked among the recent commits of the branch.
+/// * `squash` - Like `fixup`, but writes `squash! <subject>` followed by the
+///   body, whose message is kept when the commits are squashed. Neither
+///   takes the trailer flags (`signoff`, `co_authors`, `closes`, `refs`,
+///   `issue`).
+/// * `signoff` - Add a `Signed-off-by` trailer with the configured identity,
+///   like `git commit --signoff`.
+/// * `co_authors` - Add `Co-authored-by` trailers; each value is either a
+///   `Name <email>` identity or part of one found in the history.
+/// * `closes` - Add `Closes` trailers for the given issues (`123` or `#123`).
+/// * `refs` - Add `Refs` trailers for the given issues.
+/// * `issue` - The issue key, instead of the one detected from the branch
+///   name; an empty value leaves it out.
+/// * `author` - The author, as `Name <email>` or part of a previous author,
+///   instead of the configured identity.
+/// * `date` - The author date, instead of the current time.
+/// * `allow_empty` - Commit even when the staged tree is the same as the
+///   parent's, like `git commit --allow-empty`.
+/// * `dry_run` - Show the message, author, committer, parents and staged
+///   files of the commit without creating it; no hook or editor is run.
+#[derive(Args, Debug, Default, Clone)]
+pub(crate) struct CommitArgs {
+    #[arg(short, long)]
+    pub(crate) emoji: Option<String>,
+    #[arg(short, long)]
+    pub(crate) scope: Option<String>,
+    #[arg(short, long)]
+    pub(crate) title: Option<String>,
+    #[arg(short, long)]
+    pub(crate) body: Option<String>,
+    #[arg(long)]
+    pub(crate) breaking: Option<String>,
+    #[arg(long, value_enum)]
+    pub(crate) emoji_format: Option<EmojiFormat>,
+    #[arg(long)]
+    pub(crate) no_interactive: bool,
+    #[arg(short, long)]
+    pub(crate) no_verify: bool,
+    #[arg(long)]
+    pub(crate) edit: bool,
+    #[arg(long)]
+    pub(crate) amend: bool,
+    #[arg(
+        long, value_name = "REV", num_args = 0..=1, default_missing_value = "",
+        conflicts_with_all = ["squash", "amend", "signoff", "co_authors", "closes", "refs", "issue"]
+    )]
+    pub(crate) fixup: Option<String>,
+    #[arg(
+        long, value_name = "REV", num_args = 0..=1, default_missing_value = "",
+        conflicts_with_all = ["amend", "signoff", "co_authors", "closes", "refs", "issue"]
+    )]
+    pub(crate) squash: Option<String>,
+    #[arg(long)]
+    pub(crate) signoff: bool,
+    #[arg(long = "co-author", value_name = "AUTHOR")]
+    pub(crate) co_authors: Vec<String>,
+    #[arg(long, value_name = "ISSUE")]
+    pub(crate) closes: Vec<String>,
+    #[arg(long, value_name = "ISSUE")]
+    pub(crate) refs: Vec<String>,
+    #[arg(long, value_name = "KEY")]
+    pub(crate) issue: Option<String>,
+    #[arg(long)]
+    pub(crate) author: Option<String>,
+    #[arg(long)]
+    pub(crate) date: Option<String>,
+    #[arg(long)]
+    pub(crate) allow_empty: bool,
+    #[arg(long)]
+    pub(crate) dry_run: bool,
+}
+
+/// Arguments accepted by the `push` subcommand.
+///
+/// # Fields
+///
+/// * `no_verify` - Skip the `pre-push` hook, like `git push --no-verify`.
+#[derive(Args, Debug, Default, Clone)]
+pub(crate) struct PushArgs {
+    #[arg(long)]
+    pub(crate) no_verify: bool,
 }
 
 /// Command-line interface (CLI) argument parser for the `glyphit` tool.
@@ -32,12 +215,12 @@ pub(crate) enum Command {
 /// # Fields
 ///
 /// * `command` - The subcommand specified by the user. Determines the
-///               operation the CLI will perform.
+///   operation the CLI will perform.
 ///
 /// # Usage
 ///
 /// When running the `glyphit` executable, users can specify one of the
-/// subcommands (`add`, `commit`, or `push`) to execute the corresponding
+/// subcommands (`add`, `commit`, `push`, `lint`, `check` or `hooks`) to execute the corresponding
 /// action.
 #[derive(Parser, Debug)]
 #[command(name = "glyphit", version, about = "Emoji-powered git CLI")]
@@ -67,13 +250,108 @@ mod test {
     fn test_parse_commit_command() {
         let args = vec!["glyphit", "commit"];
         let cli = Cli::parse_from(args);
-        assert!(matches!(cli.command, Command::Commit));
+        assert!(matches!(cli.command, Command::Commit(_)));
+    }
+
+    #[test]
+    fn test_parse_commit_flags() {
+        let args = vec![
+            "glyphit", "commit", "--emoji", ":bug:", "--scope", "parser", "--title", "fix crash",
+            "--body", "details", "--breaking", "api changed", "--emoji-format", "both",
+            "--no-interactive", "-n", "--edit", "--amend", "--signoff", "--co-author", "alice",
+            "--co-author", "Bob <bob@example.com>", "--closes", "12", "--refs", "PROJ-7",
+            "--issue", "PROJ-8", "--author", "Alice <alice@example.com>", "--date", "@0",
+            "--allow-empty", "--dry-run",
+        ];
+        let cli = Cli::parse_from(args);
+
+        if let Command::Commit(commit_args) = cli.command {
+            assert_eq!(commit_args.emoji.as_deref(), Some(":bug:"));
+            assert_eq!(commit_args.scope.as_deref(), Some("parser"));
+            assert_eq!(commit_args.title.as_deref(), Some("fix crash"));
+            assert_eq!(commit_args.body.as_deref(), Some("details"));
+            assert_eq!(commit_args.breaking.as_deref(), Some("api changed"));
+            assert_eq!(commit_args.emoji_format, Some(EmojiFormat::Both));
+            assert!(commit_args.no_interactive);
+            assert!(commit_args.no_verify);
+            assert!(commit_args.edit);
+            assert!(commit_args.amend);
+            assert!(commit_args.signoff);
+            assert_eq!(commit_args.co_authors, vec!["alice", "Bob <bob@example.com>"]);
+            assert_eq!(commit_args.closes, vec!["12"]);
+            assert_eq!(commit_args.refs, vec!["PROJ-7"]);
+            assert_eq!(commit_args.issue.as_deref(), Some("PROJ-8"));
+            assert_eq!(commit_args.author.as_deref(), Some("Alice <alice@example.com>"));
+            assert_eq!(commit_args.date.as_deref(), Some("@0"));
+            assert!(commit_args.allow_empty);
+            assert!(commit_args.dry_run);
+        } else {
+            panic!("Expected Commit variant");
+        }
+    }
+
+    #[test]
+    fn test_parse_commit_autosquash_flags() {
+        let fixup = |args: &[&str]| match Cli::parse_from(args).command {
+            Command::Commit(commit_args) => (commit_args.fixup, commit_args.squash),
+            _ => panic!("Expected Commit variant"),
+        };
+
+        assert_eq!(fixup(&["glyphit", "commit", "--fixup", "HEAD~2"]), (Some("HEAD~2".to_string()), None));
+        assert_eq!(fixup(&["glyphit", "commit", "--fixup"]), (Some(String::new()), None));
+        assert_eq!(fixup(&["glyphit", "commit", "--squash", "--no-interactive"]), (None, Some(String::new())));
+        assert!(Cli::try_parse_from(["glyphit", "commit", "--fixup", "--squash"]).is_err());
+        assert!(Cli::try_parse_from(["glyphit", "commit", "--squash", "--amend"]).is_err());
+
+        // an autosquash message has no trailers
+        for flag in [&["--signoff"][..], &["--co-author", "alice"], &["--closes", "12"], &["--refs", "12"], &["--issue", "PROJ-7"]] {
+            let fixup = [&["glyphit", "commit", "--fixup"][..], flag].concat();
+            assert!(Cli::try_parse_from(fixup).is_err(), "{:?}", flag);
+            let squash = [&["glyphit", "commit", "--squash", "HEAD"][..], flag].concat();
+            assert!(Cli::try_parse_from(squash).is_err(), "{:?}", flag);
+        }
     }
 
     #[test]
     fn test_parse_push_command() {
         let args = vec!["glyphit", "push"];
         let cli = Cli::parse_from(args);
-        assert!(matches!(cli.command, Command::Push));
+        assert!(matches!(cli.command, Command::Push(PushArgs { no_verify: false })));
+
+        let cli = Cli::parse_from(vec!["glyphit", "push", "--no-verify"]);
+        assert!(matches!(cli.command, Command::Push(PushArgs { no_verify: true })));
+    }
+
+    #[test]
+    fn test_parse_lint_command() {
+        let cli = Cli::parse_from(vec!["glyphit", "lint", ".git/COMMIT_EDITMSG"]);
+        assert!(matches!(cli.command, Command::Lint { file: Some(ref f) } if f == ".git/COMMIT_EDITMSG"));
+
+        let cli = Cli::parse_from(vec!["glyphit", "lint"]);
+        assert!(matches!(cli.command, Command::Lint { file: None }));
+    }
+
+    #[test]
+    fn test_parse_check_command() {
+        let cli = Cli::parse_from(vec!["glyphit", "check", "origin/main..HEAD"]);
+        assert!(matches!(cli.command, Command::Check { ref range, format: ReportFormat::Text } if range == "origin/main..HEAD"));
+
+        let cli = Cli::parse_from(vec!["glyphit", "check", "HEAD~3..HEAD", "--format", "junit"]);
+        assert!(matches!(cli.command, Command::Check { format: ReportFormat::Junit, .. }));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_parse_hooks_command() {
+        let cli = Cli::parse_from(vec!["glyphit", "hooks", "install", "--prepare-commit-msg", "--force"]);
+        assert!(matches!(
+            cli.command,
+            Command::Hooks { action: HooksAction::Install { prepare_commit_msg: true, force: true } }
+        ));
+
+        let cli = Cli::parse_from(vec!["glyphit", "hooks", "prepare-commit-msg", ".git/COMMIT_EDITMSG", "message"]);
+        assert!(matches!(
+            cli.command,
+            Command::Hooks { action: HooksAction::PrepareCommitMsg { ref source, .. } } if source.as_deref() == Some("message")
+        ));
+    }
+}
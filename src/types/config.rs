@@ -0,0 +1,499 @@
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use git2::{Error, ErrorCode, Repository};
+use serde::Deserialize;
+use crate::types::emoji::EmojiFormat;
+use crate::types::template::GITMOJI;
+
+/// Name of the repository-level configuration file, looked up at the root
+/// of the working directory.
+pub const REPOSITORY_CONFIG_FILE: &str = ".glyphit.toml";
+
+/// A user-defined suggestion rule, configured as `[[suggestions]]` entries.
+///
+/// # Fields
+///
+/// * `pattern` - A regular expression matched against the staged paths.
+/// * `emoji` - The shortcode (or glyph) suggested when the rule matches.
+/// * `only` - When `true`, the rule matches only if every staged path matches
+///   the pattern; otherwise a single matching path is enough.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SuggestionRule {
+    pub pattern: String,
+    pub emoji: String,
+    #[serde(default)]
+    pub only: bool,
+}
+
+/// Where the emoji usage history, used to rank the picker, comes from.
+///
+/// # Possible values
+///
+/// * `State` - A per-user state file updated after every commit.
+/// * `Commits` - The recent commits of `user.email` in the repository.
+/// * `Off` - No history; the picker keeps the catalog order.
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum HistoryMode {
+    #[default]
+    State,
+    Commits,
+    Off,
+}
+
+impl std::str::FromStr for HistoryMode {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "state" => Ok(HistoryMode::State),
+            "commits" => Ok(HistoryMode::Commits),
+            "off" | "false" => Ok(HistoryMode::Off),
+            other => Err(Error::from_str(
+                format!("unknown history mode '{}', expected state, commits or off", other).as_str()
+            )),
+        }
+    }
+}
+
+/// Where the issue key detected from the branch name is written.
+///
+/// # Possible values
+///
+/// * `Trailer` - In a `Refs: <key>` trailer.
+/// * `Subject` - At the start of the subject: `✨ PROJ-421 add login form`.
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum IssuePlacement {
+    #[default]
+    Trailer,
+    Subject,
+}
+
+impl std::str::FromStr for IssuePlacement {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "trailer" => Ok(IssuePlacement::Trailer),
+            "subject" => Ok(IssuePlacement::Subject),
+            other => Err(Error::from_str(
+                format!("unknown issue placement '{}', expected trailer or subject", other).as_str()
+            )),
+        }
+    }
+}
+
+/// The resolved glyphit configuration.
+///
+/// Values are layered, each source overriding the previous one:
+///
+/// 1. built-in defaults;
+/// 2. the user file, `$XDG_CONFIG_HOME/glyphit/config.toml`
+///    (or `~/.config/glyphit/config.toml`);
+/// 3. the repository file, `.glyphit.toml` at the working directory root;
+/// 4. the `glyphit.*` git configuration keys.
+///
+/// # Fields
+///
+/// * `allowed_emojis` - Glyphs or shortcodes that may be used; `None` allows
+///   the whole catalog. (`allowed_emojis`, `glyphit.allowedEmojis`)
+/// * `catalog` - Path of a user emoji catalog merged into the built-in one.
+///   (`catalog`, `glyphit.catalog`)
+/// * `default_remote` - Remote used by `push`. (`default_remote`,
+///   `glyphit.defaultRemote`)
+/// * `emoji_format` - How the emoji is written in the subject.
+///   (`emoji_format`, `glyphit.emojiFormat`)
+/// * `scopes` - The scopes that may be used; `None` allows any scope.
+///   (`scopes`, `glyphit.scopes`)
+/// * `history` - Where the usage history ranking the emoji picker comes
+///   from. (`history`, `glyphit.history`)
+/// * `interactive` - Whether missing values are prompted for.
+///   (`interactive`, `glyphit.interactive`)
+/// * `issue_pattern` - A regular expression extracting the issue key from
+///   the branch name (its first group, or the whole match); `None` disables
+///   the detection. (`issue_pattern`, `glyphit.issuePattern`)
+/// * `issue_placement` - Where the issue key is written.
+///   (`issue_placement`, `glyphit.issuePlacement`)
+/// * `require_issue_branches` - Branch patterns (`*` matches anything) on
+///   which a commit without an issue key is refused.
+///   (`require_issue_branches`, `glyphit.requireIssueBranches`)
+/// * `skip_merges` - Whether `glyphit check` skips merge commits.
+///   (`skip_merges`, `glyphit.skipMerges`)
+/// * `suggestions` - Extra rules used to suggest an emoji from the staged
+///   files. (`[[suggestions]]` tables only)
+/// * `subject_max_length` - Maximum length of the subject line, in
+///   characters. (`subject_max_length`, `glyphit.subjectMaxLength`)
+/// * `template` - The message template: a built-in name (`gitmoji`,
+///   `conventional`) or a custom template. (`template`, `glyphit.template`)
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    pub allowed_emojis: Option<Vec<String>>,
+    pub catalog: Option<PathBuf>,
+    pub default_remote: String,
+    pub emoji_format: EmojiFormat,
+    pub history: HistoryMode,
+    pub interactive: bool,
+    pub issue_pattern: Option<String>,
+    pub issue_placement: IssuePlacement,
+    pub require_issue_branches: Vec<String>,
+    pub scopes: Option<Vec<String>>,
+    pub skip_merges: bool,
+    pub subject_max_length: usize,
+    pub suggestions: Vec<SuggestionRule>,
+    pub template: String,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            allowed_emojis: None,
+            catalog: None,
+            default_remote: "origin".to_string(),
+            emoji_format: EmojiFormat::default(),
+            history: HistoryMode::default(),
+            interactive: true,
+            issue_pattern: None,
+            issue_placement: IssuePlacement::default(),
+            require_issue_branches: Vec::new(),
+            scopes: None,
+            skip_merges: true,
+            subject_max_length: 72,
+            suggestions: Vec::new(),
+            template: GITMOJI.to_string(),
+        }
+    }
+}
+
+// one configuration layer, as read from a file or from git config
+#[derive(Deserialize, Debug, Default)]
+struct ConfigLayer {
+    allowed_emojis: Option<Vec<String>>,
+    catalog: Option<PathBuf>,
+    default_remote: Option<String>,
+    emoji_format: Option<EmojiFormat>,
+    history: Option<HistoryMode>,
+    interactive: Option<bool>,
+    issue_pattern: Option<String>,
+    issue_placement: Option<IssuePlacement>,
+    require_issue_branches: Option<Vec<String>>,
+    scopes: Option<Vec<String>>,
+    skip_merges: Option<bool>,
+    subject_max_length: Option<usize>,
+    suggestions: Option<Vec<SuggestionRule>>,
+    template: Option<String>,
+}
+
+// splits a comma or space separated git config list
+fn split_list(value: &str) -> Vec<String> {
+    value.split([',', ' ']).filter(|s| !s.is_empty()).map(str::to_string).collect()
+}
+
+/// Turns the result of reading a git configuration value into `None` when
+/// the key is not set, so that only the other errors (a value that cannot be
+/// read as the expected type) are reported.
+///
+/// # Errors
+///
+/// Returns the `Err(Error)` of `value`, unless its code is `NotFound`.
+pub fn optional<T>(value: Result<T, Error>) -> Result<Option<T>, Error> {
+    match value {
+        Ok(value) => Ok(Some(value)),
+        Err(e) if e.code() == ErrorCode::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+impl ConfigLayer {
+    // reads a TOML layer, if the file exists; relative paths are resolved
+    // against `base`
+    fn from_file(path: &Path, base: &Path) -> Result<Option<Self>, Error> {
+        let content = match std::fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(Error::from_str(
+                format!("cannot read configuration {}: {}", path.display(), e).as_str()
+            )),
+        };
+        let mut layer: ConfigLayer = toml::from_str(&content)
+            .map_err(|e| Error::from_str(format!("invalid configuration {}: {}", path.display(), e.message()).as_str()))?;
+        layer.catalog = layer.catalog.map(|catalog| base.join(catalog));
+        Ok(Some(layer))
+    }
+
+    // reads the `glyphit.*` git configuration keys
+    fn from_git(repo: &Repository) -> Result<Self, Error> {
+        let config = repo.config()?;
+        let workdir = repo.workdir().unwrap_or_else(|| repo.path());
+        Ok(Self {
+            allowed_emojis: optional(config.get_string("glyphit.allowedEmojis"))?.map(|value| split_list(&value)),
+            catalog: optional(config.get_path("glyphit.catalog"))?.map(|catalog| workdir.join(catalog)),
+            default_remote: optional(config.get_string("glyphit.defaultRemote"))?,
+            emoji_format: EmojiFormat::from_config(&config)?,
+            history: match optional(config.get_string("glyphit.history"))? {
+                Some(value) => Some(value.parse()?),
+                None => None,
+            },
+            interactive: optional(config.get_bool("glyphit.interactive"))?,
+            issue_pattern: optional(config.get_string("glyphit.issuePattern"))?,
+            issue_placement: match optional(config.get_string("glyphit.issuePlacement"))? {
+                Some(value) => Some(value.parse()?),
+                None => None,
+            },
+            require_issue_branches: optional(config.get_string("glyphit.requireIssueBranches"))?
+                .map(|value| split_list(&value)),
+            scopes: optional(config.get_string("glyphit.scopes"))?.map(|value| split_list(&value)),
+            skip_merges: optional(config.get_bool("glyphit.skipMerges"))?,
+            subject_max_length: match optional(config.get_i64("glyphit.subjectMaxLength"))? {
+                Some(length) => Some(usize::try_from(length)
+                    .map_err(|_| Error::from_str("glyphit.subjectMaxLength must be a positive number"))?),
+                None => None,
+            },
+            suggestions: None,
+            template: optional(config.get_string("glyphit.template"))?,
+        })
+    }
+
+    fn apply(self, config: &mut Config) {
+        if self.allowed_emojis.is_some() {
+            config.allowed_emojis = self.allowed_emojis;
+        }
+        if self.catalog.is_some() {
+            config.catalog = self.catalog;
+        }
+        if let Some(remote) = self.default_remote {
+            config.default_remote = remote;
+        }
+        if let Some(format) = self.emoji_format {
+            config.emoji_format = format;
+        }
+        if let Some(history) = self.history {
+            config.history = history;
+        }
+        if let Some(interactive) = self.interactive {
+            config.interactive = interactive;
+        }
+        if self.issue_pattern.is_some() {
+            config.issue_pattern = self.issue_pattern;
+        }
+        if let Some(placement) = self.issue_placement {
+            config.issue_placement = placement;
+        }
+        if let Some(branches) = self.require_issue_branches {
+            config.require_issue_branches = branches;
+        }
+        if self.scopes.is_some() {
+            config.scopes = self.scopes;
+        }
+        if let Some(skip_merges) = self.skip_merges {
+            config.skip_merges = skip_merges;
+        }
+        if let Some(length) = self.subject_max_length {
+            config.subject_max_length = length;
+        }
+        if let Some(suggestions) = self.suggestions {
+            config.suggestions.extend(suggestions);
+        }
+        if let Some(template) = self.template {
+            config.template = template;
+        }
+    }
+}
+
+/// Returns the path of the user-level configuration file, if a configuration
+/// directory can be determined.
+pub fn user_config_path() -> Option<PathBuf> {
+    user_config_path_with_env(&|name| std::env::var_os(name))
+}
+
+// same as `user_config_path`, reading the environment through `env`
+fn user_config_path_with_env(env: &dyn Fn(&str) -> Option<OsString>) -> Option<PathBuf> {
+    let base = match env("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => match (env("HOME"), env("APPDATA")) {
+            (Some(home), _) => PathBuf::from(home).join(".config"),
+            (None, Some(appdata)) => PathBuf::from(appdata),
+            (None, None) => return None,
+        },
+    };
+    Some(base.join("glyphit").join("config.toml"))
+}
+
+impl Config {
+    /// Loads the configuration of a repository, merging the user file, the
+    /// repository `.glyphit.toml` and the `glyphit.*` git configuration keys.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `Err(Error)` if a configuration file is not valid TOML, or a
+    /// git configuration key holds an invalid value.
+    pub fn load(repo: &Repository) -> Result<Self, Error> {
+        Self::load_with_user_file(repo, user_config_path().as_deref())
+    }
+
+    // same as `load`, with an explicit user-level file
+    fn load_with_user_file(repo: &Repository, user_file: Option<&Path>) -> Result<Self, Error> {
+        let mut config = Config::default();
+
+        if let Some(path) = user_file {
+            let base = path.parent().unwrap_or(Path::new("."));
+            if let Some(layer) = ConfigLayer::from_file(path, base)? {
+                layer.apply(&mut config);
+            }
+        }
+        if let Some(workdir) = repo.workdir()
+            && let Some(layer) = ConfigLayer::from_file(&workdir.join(REPOSITORY_CONFIG_FILE), workdir)? {
+            layer.apply(&mut config);
+        }
+        ConfigLayer::from_git(repo)?.apply(&mut config);
+
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_defaults() {
+        let temp_dir = tempdir().unwrap();
+        let repo = Repository::init(temp_dir.path()).unwrap();
+
+        let config = Config::load_with_user_file(&repo, None).unwrap();
+
+        assert_eq!(config, Config::default());
+    }
+
+    #[test]
+    fn test_layers_override_each_other() {
+        let temp_dir = tempdir().unwrap();
+        let user_dir = tempdir().unwrap();
+        let repo = Repository::init(temp_dir.path()).unwrap();
+
+        let user_file = user_dir.path().join("config.toml");
+        std::fs::write(
+            &user_file,
+            "default_remote = \"upstream\"\nsubject_max_length = 50\ninteractive = false\nhistory = \"commits\"\n\
+             [[suggestions]]\npattern = \"\\\\.css$\"\nemoji = \"lipstick\"\n",
+        ).unwrap();
+        std::fs::write(
+            temp_dir.path().join(REPOSITORY_CONFIG_FILE),
+            "subject_max_length = 60\nallowed_emojis = [\"bug\", \"✨\"]\nemoji_format = \"both\"\ncatalog = \"emojis.toml\"\n\
+             [[suggestions]]\npattern = \"^migrations/\"\nemoji = \"card_file_box\"\nonly = true\n",
+        ).unwrap();
+        repo.config().unwrap().set_str("glyphit.emojiFormat", "shortcode").unwrap();
+
+        let config = Config::load_with_user_file(&repo, Some(&user_file)).unwrap();
+
+        assert_eq!(config.default_remote, "upstream");
+        assert_eq!(config.history, HistoryMode::Commits);
+        assert!(!config.interactive);
+        assert_eq!(config.subject_max_length, 60);
+        assert_eq!(config.allowed_emojis, Some(vec!["bug".to_string(), "✨".to_string()]));
+        assert_eq!(config.emoji_format, EmojiFormat::Shortcode);
+        assert_eq!(config.catalog, Some(repo.workdir().unwrap().join("emojis.toml")));
+        assert_eq!(config.suggestions, vec![
+            SuggestionRule { pattern: "\\.css$".to_string(), emoji: "lipstick".to_string(), only: false },
+            SuggestionRule { pattern: "^migrations/".to_string(), emoji: "card_file_box".to_string(), only: true },
+        ]);
+    }
+
+    #[test]
+    fn test_git_config_keys() {
+        let temp_dir = tempdir().unwrap();
+        let repo = Repository::init(temp_dir.path()).unwrap();
+        let mut git_config = repo.config().unwrap();
+        git_config.set_str("glyphit.allowedEmojis", "bug, sparkles").unwrap();
+        git_config.set_str("glyphit.defaultRemote", "fork").unwrap();
+        git_config.set_bool("glyphit.interactive", false).unwrap();
+        git_config.set_i64("glyphit.subjectMaxLength", 100).unwrap();
+        git_config.set_str("glyphit.template", "conventional").unwrap();
+        git_config.set_str("glyphit.scopes", "cli,parser").unwrap();
+        git_config.set_str("glyphit.history", "off").unwrap();
+        git_config.set_bool("glyphit.skipMerges", false).unwrap();
+        git_config.set_str("glyphit.issuePattern", "[A-Z]+-[0-9]+").unwrap();
+        git_config.set_str("glyphit.issuePlacement", "subject").unwrap();
+        git_config.set_str("glyphit.requireIssueBranches", "main, release/*").unwrap();
+
+        let config = Config::load_with_user_file(&repo, None).unwrap();
+
+        assert_eq!(config.allowed_emojis, Some(vec!["bug".to_string(), "sparkles".to_string()]));
+        assert_eq!(config.default_remote, "fork");
+        assert!(!config.interactive);
+        assert_eq!(config.subject_max_length, 100);
+        assert_eq!(config.template, "conventional");
+        assert_eq!(config.scopes, Some(vec!["cli".to_string(), "parser".to_string()]));
+        assert_eq!(config.history, HistoryMode::Off);
+        assert!(!config.skip_merges);
+        assert_eq!(config.issue_pattern.as_deref(), Some("[A-Z]+-[0-9]+"));
+        assert_eq!(config.issue_placement, IssuePlacement::Subject);
+        assert_eq!(config.require_issue_branches, vec!["main", "release/*"]);
+    }
+
+    #[test]
+    fn test_invalid_file() {
+        let temp_dir = tempdir().unwrap();
+        let repo = Repository::init(temp_dir.path()).unwrap();
+        std::fs::write(temp_dir.path().join(REPOSITORY_CONFIG_FILE), "emoji_format = \"html\"").unwrap();
+
+        assert!(Config::load_with_user_file(&repo, None).is_err());
+    }
+
+    #[test]
+    fn test_unreadable_values() {
+        let temp_dir = tempdir().unwrap();
+        let repo = Repository::init(temp_dir.path()).unwrap();
+
+        repo.config().unwrap().set_str("glyphit.subjectMaxLength", "long").unwrap();
+        assert!(Config::load_with_user_file(&repo, None).is_err());
+        repo.config().unwrap().set_i64("glyphit.subjectMaxLength", -1).unwrap();
+        assert!(Config::load_with_user_file(&repo, None).is_err());
+        repo.config().unwrap().remove("glyphit.subjectMaxLength").unwrap();
+
+        // a string that is not UTF-8, a path that cannot be expanded
+        let mut git_config = std::fs::read(repo.path().join("config")).unwrap();
+        git_config.extend_from_slice(b"[glyphit]\n\ttemplate = \xff\xfe\n");
+        std::fs::write(repo.path().join("config"), &git_config).unwrap();
+        assert!(Config::load_with_user_file(&repo, None).is_err());
+        repo.config().unwrap().remove("glyphit.template").unwrap();
+        repo.config().unwrap().set_str("glyphit.catalog", "~nobody/emojis.toml").unwrap();
+        assert!(Config::load_with_user_file(&repo, None).is_err());
+        repo.config().unwrap().remove("glyphit.catalog").unwrap();
+        assert!(Config::load_with_user_file(&repo, None).is_ok());
+
+        // a configuration file that exists but cannot be read is not skipped
+        std::fs::write(temp_dir.path().join(REPOSITORY_CONFIG_FILE), [0xff, 0xfe]).unwrap();
+        assert!(Config::load_with_user_file(&repo, None).is_err());
+        std::fs::remove_file(temp_dir.path().join(REPOSITORY_CONFIG_FILE)).unwrap();
+        assert!(Config::load_with_user_file(&repo, None).is_ok());
+    }
+
+    #[test]
+    fn test_user_config_path() {
+        let config_home = tempdir().unwrap();
+        let temp_dir = tempdir().unwrap();
+        let repo = Repository::init(temp_dir.path()).unwrap();
+
+        let env = |name: &str| match name {
+            "XDG_CONFIG_HOME" => Some(config_home.path().as_os_str().to_owned()),
+            _ => None,
+        };
+        let user_file = user_config_path_with_env(&env).unwrap();
+        assert_eq!(user_file, config_home.path().join("glyphit").join("config.toml"));
+
+        std::fs::create_dir_all(user_file.parent().unwrap()).unwrap();
+        std::fs::write(&user_file, "default_remote = \"upstream\"\n").unwrap();
+        let config = Config::load_with_user_file(&repo, Some(&user_file)).unwrap();
+        assert_eq!(config.default_remote, "upstream");
+
+        let env = |name: &str| match name {
+            "XDG_CONFIG_HOME" => Some(OsString::new()),
+            "HOME" => Some(OsString::from("/home/jane")),
+            _ => None,
+        };
+        assert_eq!(user_config_path_with_env(&env), Some(PathBuf::from("/home/jane/.config/glyphit/config.toml")));
+        assert_eq!(user_config_path_with_env(&|_| None), None);
+    }
+}
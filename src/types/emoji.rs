@@ -0,0 +1,424 @@
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+use clap::ValueEnum;
+use git2::{Error, ErrorCode};
+use serde::Deserialize;
+use crate::types::config::Config;
+
+// the built-in gitmoji catalog, shipped inside the binary
+const BUILTIN_CATALOG: &str = include_str!("emojis.toml");
+
+/// The semantic version bump implied by a commit using a given emoji.
+///
+/// # Possible values
+///
+/// * `None` - The change does not affect the released artifact.
+/// * `Patch` - A backwards compatible fix.
+/// * `Minor` - A backwards compatible feature.
+/// * `Major` - A breaking change.
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SemverImpact {
+    #[default]
+    None,
+    Patch,
+    Minor,
+    Major,
+}
+
+/// How an emoji is written into the commit subject.
+///
+/// # Possible values
+///
+/// * `Unicode` - The raw glyph, e.g. `✨`.
+/// * `Shortcode` - The gitmoji shortcode, e.g. `:sparkles:`.
+/// * `Both` - The glyph followed by the shortcode, e.g. `✨ :sparkles:`.
+#[derive(Deserialize, ValueEnum, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum EmojiFormat {
+    #[default]
+    Unicode,
+    Shortcode,
+    Both,
+}
+
+impl EmojiFormat {
+    /// Reads the format from the `glyphit.emojiFormat` git configuration key.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `Err(Error)` if the key holds an unknown format.
+    pub fn from_config(config: &git2::Config) -> Result<Option<Self>, Error> {
+        match config.get_string("glyphit.emojiFormat") {
+            Ok(value) => value.parse().map(Some),
+            Err(e) if e.code() == ErrorCode::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+impl FromStr for EmojiFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "unicode" => Ok(EmojiFormat::Unicode),
+            "shortcode" => Ok(EmojiFormat::Shortcode),
+            "both" => Ok(EmojiFormat::Both),
+            other => Err(Error::from_str(
+                format!("unknown emoji format '{}', expected unicode, shortcode or both", other).as_str()
+            )),
+        }
+    }
+}
+
+/// A single entry of the emoji catalog.
+///
+/// # Fields
+///
+/// * `glyph` - The unicode glyph written into commit messages (e.g. `✨`).
+/// * `shortcode` - The gitmoji shortcode, without colons (e.g. `sparkles`).
+/// * `description` - A short human-readable description of the intent.
+/// * `keywords` - Extra aliases used when searching the catalog (e.g. `deps`).
+/// * `semver_impact` - The version bump implied by the emoji.
+/// * `conventional_type` - The Conventional Commits type the emoji maps to, if any.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Emoji {
+    pub glyph: String,
+    pub shortcode: String,
+    pub description: String,
+    #[serde(default)]
+    pub keywords: Vec<String>,
+    #[serde(default)]
+    pub semver_impact: SemverImpact,
+    #[serde(default)]
+    pub conventional_type: Option<String>,
+}
+
+impl Emoji {
+    /// Renders the emoji as it should appear in a commit subject.
+    pub fn render(&self, format: EmojiFormat) -> String {
+        match format {
+            EmojiFormat::Unicode => self.glyph.clone(),
+            EmojiFormat::Shortcode => format!(":{}:", self.shortcode),
+            EmojiFormat::Both => format!("{} :{}:", self.glyph, self.shortcode),
+        }
+    }
+}
+
+impl fmt::Display for Emoji {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} :{}: {}", self.glyph, self.shortcode, self.description)
+    }
+}
+
+// emoji variation selector, optional in user input (`♻` vs `♻️`)
+const VARIATION_SELECTOR: char = '\u{FE0F}';
+
+// compares two glyphs ignoring emoji variation selectors
+fn same_glyph(a: &str, b: &str) -> bool {
+    a.chars().filter(|c| *c != VARIATION_SELECTOR)
+        .eq(b.chars().filter(|c| *c != VARIATION_SELECTOR))
+}
+
+// length of `glyph` at the start of `text`, ignoring variation selectors
+fn glyph_prefix_len(text: &str, glyph: &str) -> Option<usize> {
+    let mut expected = glyph.chars().filter(|c| *c != VARIATION_SELECTOR).peekable();
+    let mut len = 0;
+    for c in text.chars() {
+        if expected.peek().is_none() {
+            break;
+        }
+        if c == VARIATION_SELECTOR {
+            len += c.len_utf8();
+            continue;
+        }
+        if expected.next() != Some(c) {
+            return None;
+        }
+        len += c.len_utf8();
+    }
+    if expected.peek().is_some() {
+        return None;
+    }
+    // a trailing variation selector belongs to the glyph
+    if text[len..].starts_with(VARIATION_SELECTOR) {
+        len += VARIATION_SELECTOR.len_utf8();
+    }
+    Some(len)
+}
+
+// shape of a catalog file: a list of `[[emoji]]` tables
+#[derive(Deserialize)]
+struct CatalogFile {
+    #[serde(default)]
+    emoji: Vec<Emoji>,
+}
+
+/// The registry of emojis known to glyphit.
+///
+/// The catalog is the single source used by the emoji prompt, the message
+/// builder and any validator. It starts from the built-in gitmoji list and can
+/// be extended with a user catalog using the same TOML layout:
+///
+/// ```toml
+/// [[emoji]]
+/// glyph = "🦀"
+/// shortcode = "crab"
+/// description = "Rewrite it in Rust"
+/// keywords = ["rust", "ferris"]
+/// semver_impact = "minor"
+/// conventional_type = "refactor"
+/// ```
+#[derive(Debug, Clone)]
+pub struct EmojiCatalog {
+    emojis: Vec<Emoji>,
+}
+
+impl EmojiCatalog {
+    /// Returns the built-in gitmoji catalog.
+    pub fn builtin() -> Self {
+        Self::from_toml(BUILTIN_CATALOG).expect("the built-in emoji catalog is valid TOML")
+    }
+
+    /// Parses a catalog from the content of a TOML document.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `Err(Error)` if the document is not a valid catalog.
+    pub fn from_toml(content: &str) -> Result<Self, Error> {
+        let file: CatalogFile = toml::from_str(content)
+            .map_err(|e| Error::from_str(format!("invalid emoji catalog: {}", e.message()).as_str()))?;
+        let emojis = file.emoji.into_iter()
+            .map(|mut emoji| {
+                emoji.shortcode = emoji.shortcode.trim_matches(':').to_string();
+                emoji
+            })
+            .collect();
+        Ok(Self { emojis })
+    }
+
+    /// Reads and parses a catalog file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `Err(Error)` if the file cannot be read or is not a valid catalog.
+    pub fn from_file(path: &Path) -> Result<Self, Error> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| Error::from_str(format!("cannot read emoji catalog {}: {}", path.display(), e).as_str()))?;
+        Self::from_toml(&content)
+    }
+
+    /// Loads the catalog described by a configuration.
+    ///
+    /// The built-in catalog is extended with the user catalog referenced by
+    /// `config.catalog`, if any, then restricted to `config.allowed_emojis`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `Err(Error)` if the user catalog is invalid.
+    pub fn load(config: &Config) -> Result<Self, Error> {
+        let mut catalog = Self::builtin();
+        if let Some(path) = &config.catalog {
+            catalog.merge(Self::from_file(path)?);
+        }
+        if let Some(allowed) = &config.allowed_emojis {
+            catalog.restrict(allowed);
+        }
+        Ok(catalog)
+    }
+
+    /// Merges another catalog into this one.
+    ///
+    /// Entries sharing a shortcode with an existing emoji replace it in place,
+    /// new entries are appended at the end.
+    pub fn merge(&mut self, other: EmojiCatalog) {
+        for emoji in other.emojis {
+            match self.emojis.iter_mut().find(|e| e.shortcode == emoji.shortcode) {
+                Some(existing) => *existing = emoji,
+                None => self.emojis.push(emoji),
+            }
+        }
+    }
+
+    /// Keeps only the emojis whose glyph or shortcode appears in `allowed`.
+    pub fn restrict(&mut self, allowed: &[String]) {
+        self.emojis.retain(|e| allowed.iter().any(|a| {
+            same_glyph(&e.glyph, a.trim()) || e.shortcode == a.trim().trim_matches(':')
+        }));
+    }
+
+    /// Looks up an emoji by glyph or by shortcode, with or without colons.
+    ///
+    /// Glyphs are compared as whole grapheme sequences, ignoring the emoji
+    /// variation selector, so `♻` finds `♻️` and `⚡️` finds `⚡`. The returned
+    /// entry always carries the catalog glyph, with every code point intact.
+    pub fn find(&self, query: &str) -> Option<&Emoji> {
+        let query = query.trim();
+        let shortcode = query.trim_matches(':');
+        self.emojis.iter().find(|e| same_glyph(&e.glyph, query) || e.shortcode == shortcode)
+    }
+
+    /// Finds the emoji a commit message starts with, written either as a glyph
+    /// (with or without variation selector) or as a `:shortcode:`.
+    ///
+    /// Returns the emoji together with the length, in bytes, of the prefix it
+    /// occupies in `message`.
+    pub fn match_prefix(&self, message: &str) -> Option<(&Emoji, usize)> {
+        if let Some(rest) = message.strip_prefix(':')
+            && let Some(end) = rest.find(':') {
+            let shortcode = &rest[..end];
+            if let Some(emoji) = self.emojis.iter().find(|e| e.shortcode == shortcode) {
+                return Some((emoji, end + 2));
+            }
+        }
+
+        // longest glyph first, so that a ZWJ sequence wins over its first code point
+        self.emojis.iter()
+            .filter_map(|emoji| glyph_prefix_len(message, &emoji.glyph).map(|len| (emoji, len)))
+            .max_by_key(|(emoji, _)| emoji.glyph.len())
+    }
+
+    /// Returns all the emojis of the catalog, in display order.
+    pub fn emojis(&self) -> &[Emoji] {
+        &self.emojis
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_builtin_catalog() {
+        let catalog = EmojiCatalog::builtin();
+        assert_eq!(catalog.emojis().len(), 66);
+
+        let sparkles = catalog.find(":sparkles:").unwrap();
+        assert_eq!(sparkles.glyph, "✨");
+        assert_eq!(sparkles.semver_impact, SemverImpact::Minor);
+        assert_eq!(sparkles.conventional_type.as_deref(), Some("feat"));
+        assert_eq!(sparkles.to_string(), "✨ :sparkles: Introduce new features");
+    }
+
+    #[test]
+    fn test_find_by_glyph_and_shortcode() {
+        let catalog = EmojiCatalog::builtin();
+        assert_eq!(catalog.find("🐛").unwrap().shortcode, "bug");
+        assert_eq!(catalog.find("bug").unwrap().glyph, "🐛");
+        assert!(catalog.find("nope").is_none());
+    }
+
+    #[test]
+    fn test_find_multi_codepoint_glyphs() {
+        let catalog = EmojiCatalog::builtin();
+        assert_eq!(catalog.find("♻️").unwrap().shortcode, "recycle");
+        assert_eq!(catalog.find("♻").unwrap().glyph, "♻\u{FE0F}");
+        assert_eq!(catalog.find("⬇").unwrap().glyph, "⬇\u{FE0F}");
+        assert_eq!(catalog.find("🚑").unwrap().glyph, "🚑\u{FE0F}");
+        assert_eq!(catalog.find("⚡\u{FE0F}").unwrap().shortcode, "zap");
+        assert_eq!(catalog.find("🧑\u{200D}💻").unwrap().shortcode, "technologist");
+        // a ZWJ sequence is not matched by one of its fragments
+        assert!(catalog.find("🧑").is_none());
+    }
+
+    #[test]
+    fn test_render_formats() {
+        let catalog = EmojiCatalog::builtin();
+        let recycle = catalog.find("recycle").unwrap();
+        assert_eq!(recycle.render(EmojiFormat::Unicode), "♻\u{FE0F}");
+        assert_eq!(recycle.render(EmojiFormat::Shortcode), ":recycle:");
+        assert_eq!(recycle.render(EmojiFormat::Both), "♻\u{FE0F} :recycle:");
+    }
+
+    #[test]
+    fn test_emoji_format_from_config() {
+        let temp_dir = tempdir().unwrap();
+        let repo = git2::Repository::init(temp_dir.path()).unwrap();
+        let mut config = repo.config().unwrap();
+
+        assert_eq!(EmojiFormat::from_config(&config).unwrap(), None);
+        config.set_str("glyphit.emojiFormat", "Shortcode").unwrap();
+        assert_eq!(EmojiFormat::from_config(&config).unwrap(), Some(EmojiFormat::Shortcode));
+        config.set_str("glyphit.emojiFormat", "html").unwrap();
+        assert!(EmojiFormat::from_config(&config).is_err());
+
+        // a value that cannot be read is reported, not taken as unset
+        config.remove("glyphit.emojiFormat").unwrap();
+        let mut content = std::fs::read(repo.path().join("config")).unwrap();
+        content.extend_from_slice(b"[glyphit]\n\temojiFormat = \xff\n");
+        std::fs::write(repo.path().join("config"), &content).unwrap();
+        let config = repo.config().unwrap();
+        assert!(EmojiFormat::from_config(&config).is_err());
+    }
+
+    #[test]
+    fn test_match_prefix() {
+        let catalog = EmojiCatalog::builtin();
+
+        let (emoji, len) = catalog.match_prefix("♻️ refactor parser").unwrap();
+        assert_eq!((emoji.shortcode.as_str(), len), ("recycle", "♻️".len()));
+        let (emoji, len) = catalog.match_prefix("♻ refactor parser").unwrap();
+        assert_eq!((emoji.shortcode.as_str(), len), ("recycle", "♻".len()));
+        let (emoji, len) = catalog.match_prefix(":bug: fix").unwrap();
+        assert_eq!((emoji.shortcode.as_str(), len), ("bug", 5));
+        let (emoji, _) = catalog.match_prefix("🧑‍💻 dx").unwrap();
+        assert_eq!(emoji.shortcode, "technologist");
+        let (emoji, len) = catalog.match_prefix("⚡️ faster").unwrap();
+        assert_eq!((emoji.shortcode.as_str(), len), ("zap", "⚡️".len()));
+
+        assert!(catalog.match_prefix("fix: no emoji").is_none());
+        assert!(catalog.match_prefix(":unknown: no emoji").is_none());
+        assert!(catalog.match_prefix("").is_none());
+    }
+
+    #[test]
+    fn test_merge_user_catalog() {
+        let mut catalog = EmojiCatalog::builtin();
+        let user = EmojiCatalog::from_toml(r#"
+            [[emoji]]
+            glyph = "🦀"
+            shortcode = ":crab:"
+            description = "Rewrite it in Rust"
+
+            [[emoji]]
+            glyph = "🐞"
+            shortcode = "bug"
+            description = "Squash a bug"
+            semver_impact = "patch"
+            conventional_type = "fix"
+        "#).unwrap();
+
+        catalog.merge(user);
+
+        assert_eq!(catalog.emojis().len(), 67);
+        let crab = catalog.find("crab").unwrap();
+        assert_eq!(crab.semver_impact, SemverImpact::None);
+        assert_eq!(crab.conventional_type, None);
+        assert_eq!(catalog.find("bug").unwrap().glyph, "🐞");
+    }
+
+    #[test]
+    fn test_invalid_catalog() {
+        assert!(EmojiCatalog::from_toml("[[emoji]]\nglyph = 1").is_err());
+    }
+
+    #[test]
+    fn test_load_from_config() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("emojis.toml");
+        std::fs::write(&path, "[[emoji]]\nglyph = \"🦀\"\nshortcode = \"crab\"\ndescription = \"Rust\"\n").unwrap();
+        let config = Config {
+            catalog: Some(path),
+            allowed_emojis: Some(vec!["crab".to_string(), "🐛".to_string(), ":sparkles:".to_string()]),
+            ..Config::default()
+        };
+
+        let catalog = EmojiCatalog::load(&config).unwrap();
+
+        let shortcodes: Vec<&str> = catalog.emojis().iter().map(|e| e.shortcode.as_str()).collect();
+        assert_eq!(shortcodes, vec!["bug", "sparkles", "crab"]);
+    }
+}
@@ -1,2 +1,6 @@
 pub mod commands;
-pub mod repository;
\ No newline at end of file
+pub mod config;
+pub mod emoji;
+pub mod repository;
+pub mod staged;
+pub mod template;
\ No newline at end of file
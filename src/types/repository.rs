@@ -13,6 +13,19 @@ pub fn get_current_repository() -> Result<Repository, Error> {
     Repository::discover(".")
 }
 
+/// Returns the character starting comment lines in commit messages:
+/// `core.commentChar`, or `#` when unset or set to `auto`.
+///
+/// # Errors
+///
+/// Returns an `Err(Error)` if the repository configuration cannot be read.
+pub fn comment_char(repo: &Repository) -> Result<char, Error> {
+    Ok(match repo.config()?.get_string("core.commentChar") {
+        Ok(value) if value != "auto" => value.chars().next().unwrap_or('#'),
+        _ => '#',
+    })
+}
+
 #[cfg(test)]
 mod test {
     use crate::types::repository::get_current_repository;
@@ -23,7 +36,7 @@ mod test {
             Err(e) => panic!("{}", e.message()),
             Ok(repo) => {
                 println!("{:?}", repo.workdir().unwrap().file_name().unwrap().to_str());
-                assert!(true)
+                assert!(repo.workdir().is_some())
             }
         }
     }
@@ -0,0 +1,203 @@
+use git2::{Delta, Diff, DiffDelta, DiffFindOptions, DiffOptions, Error, Patch, Repository, Tree};
+
+/// A file changed between `HEAD` and the index.
+///
+/// # Fields
+///
+/// * `path` - The path of the file in the index, relative to the workdir.
+///   For deletions, the path the file had in `HEAD`.
+/// * `old_path` - The previous path of a renamed file.
+/// * `status` - The kind of change (added, modified, deleted, renamed, ...).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StagedChange {
+    pub path: String,
+    pub old_path: Option<String>,
+    pub status: Delta,
+}
+
+/// A staged file with its line counts, as in `git diff --stat`.
+///
+/// # Fields
+///
+/// * `change` - The staged change.
+/// * `insertions` - The number of added lines.
+/// * `deletions` - The number of removed lines.
+/// * `binary` - Whether the file is binary, in which case no lines are
+///   counted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileStat {
+    pub change: StagedChange,
+    pub insertions: usize,
+    pub deletions: usize,
+    pub binary: bool,
+}
+
+/// Returns the tree of the `HEAD` commit, or `None` on an unborn branch.
+pub fn head_tree(repo: &Repository) -> Result<Option<Tree<'_>>, Error> {
+    match repo.head() {
+        Ok(head) => Ok(Some(head.peel_to_tree()?)),
+        Err(_) => Ok(None),
+    }
+}
+
+/// Lists the changes staged for the next commit, i.e. the diff between the
+/// `HEAD` tree and the index, with rename detection.
+///
+/// # Errors
+///
+/// Returns an `Err(Error)` if `HEAD` or the index cannot be read.
+pub fn staged_changes(repo: &Repository) -> Result<Vec<StagedChange>, Error> {
+    let tree = head_tree(repo)?;
+    Ok(staged_diff(repo, tree.as_ref())?.deltas().filter_map(|delta| change_of(&delta)).collect())
+}
+
+/// Lists the changes of the working directory that are not staged: the
+/// diff between the index and the workdir, untracked files included
+/// (with the `Untracked` status) unless they are ignored.
+///
+/// # Errors
+///
+/// Returns an `Err(Error)` if the index or the working directory cannot be
+/// read, e.g. in a bare repository.
+pub fn unstaged_changes(repo: &Repository) -> Result<Vec<StagedChange>, Error> {
+    let mut options = DiffOptions::new();
+    options.include_untracked(true).recurse_untracked_dirs(true);
+    let diff = repo.diff_index_to_workdir(None, Some(&mut options))?;
+    Ok(diff.deltas().filter_map(|delta| change_of(&delta)).collect())
+}
+
+/// Lists the changes between `base` and the index with their line counts:
+/// what a commit of the index on top of `base` records. `base` is `None`
+/// for a root commit.
+///
+/// # Errors
+///
+/// Returns an `Err(Error)` if the index cannot be read or diffed.
+pub fn staged_stats(repo: &Repository, base: Option<&Tree>) -> Result<Vec<FileStat>, Error> {
+    let diff = staged_diff(repo, base)?;
+    let mut stats = Vec::new();
+    for (index, delta) in diff.deltas().enumerate() {
+        let Some(change) = change_of(&delta) else { continue };
+        let patch = Patch::from_diff(&diff, index)?;
+        let binary = delta.flags().is_binary() || patch.is_none();
+        let (_, insertions, deletions) = match &patch {
+            Some(patch) if !binary => patch.line_stats()?,
+            _ => (0, 0, 0),
+        };
+        stats.push(FileStat { change, insertions, deletions, binary });
+    }
+    Ok(stats)
+}
+
+// the diff from a tree to the index, with rename detection
+fn staged_diff<'r>(repo: &'r Repository, base: Option<&Tree>) -> Result<Diff<'r>, Error> {
+    let mut diff = repo.diff_tree_to_index(base, None, None)?;
+    diff.find_similar(Some(DiffFindOptions::new().renames(true)))?;
+    Ok(diff)
+}
+
+fn change_of(delta: &DiffDelta) -> Option<StagedChange> {
+    let path_of = |file: git2::DiffFile| file.path().map(|p| p.to_string_lossy().replace('\\', "/"));
+    let status = delta.status();
+    let new_path = path_of(delta.new_file());
+    let old_path = path_of(delta.old_file());
+    let path = match status {
+        Delta::Deleted => old_path.clone(),
+        _ => new_path.clone().or(old_path.clone()),
+    }?;
+    let old_path = match status {
+        Delta::Renamed | Delta::Copied => old_path,
+        _ => None,
+    };
+    Some(StagedChange { path, old_path, status })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use git2::Signature;
+    use tempfile::tempdir;
+    use crate::functions::add::add;
+
+    #[test]
+    fn test_staged_changes() {
+        let temp_dir = tempdir().unwrap();
+        let repo = Repository::init(temp_dir.path()).unwrap();
+
+        std::fs::write(temp_dir.path().join("moved.txt"), "some content that is long enough\n").unwrap();
+        std::fs::write(temp_dir.path().join("deleted.txt"), "bye\n").unwrap();
+        add(&[".".to_string()], Some(&repo)).unwrap();
+        assert_eq!(staged_changes(&repo).unwrap().len(), 2);
+
+        let signature = Signature::now("Test User", "test@example.com").unwrap();
+        let tree = repo.find_tree(repo.index().unwrap().write_tree().unwrap()).unwrap();
+        repo.commit(Some("HEAD"), &signature, &signature, "Initial commit", &tree, &[]).unwrap();
+
+        std::fs::create_dir(temp_dir.path().join("src")).unwrap();
+        std::fs::rename(temp_dir.path().join("moved.txt"), temp_dir.path().join("src/moved.txt")).unwrap();
+        std::fs::write(temp_dir.path().join("src/new.rs"), "fn main() {}\n").unwrap();
+        let mut index = repo.index().unwrap();
+        index.remove_path(std::path::Path::new("moved.txt")).unwrap();
+        index.remove_path(std::path::Path::new("deleted.txt")).unwrap();
+        index.write().unwrap();
+        add(&["src".to_string()], Some(&repo)).unwrap();
+
+        let mut changes = staged_changes(&repo).unwrap();
+        changes.sort_by(|a, b| a.path.cmp(&b.path));
+
+        assert_eq!(changes, vec![
+            StagedChange { path: "deleted.txt".to_string(), old_path: None, status: Delta::Deleted },
+            StagedChange { path: "src/moved.txt".to_string(), old_path: Some("moved.txt".to_string()), status: Delta::Renamed },
+            StagedChange { path: "src/new.rs".to_string(), old_path: None, status: Delta::Added },
+        ]);
+    }
+
+    #[test]
+    fn test_unstaged_changes() {
+        let temp_dir = tempdir().unwrap();
+        let repo = Repository::init(temp_dir.path()).unwrap();
+
+        std::fs::write(temp_dir.path().join("tracked.txt"), "one\n").unwrap();
+        std::fs::write(temp_dir.path().join("staged.txt"), "one\n").unwrap();
+        add(&[".".to_string()], Some(&repo)).unwrap();
+
+        std::fs::write(temp_dir.path().join("tracked.txt"), "two\n").unwrap();
+        std::fs::create_dir(temp_dir.path().join("notes")).unwrap();
+        std::fs::write(temp_dir.path().join("notes/todo.md"), "- test\n").unwrap();
+
+        let mut changes = unstaged_changes(&repo).unwrap();
+        changes.sort_by(|a, b| a.path.cmp(&b.path));
+        assert_eq!(changes, vec![
+            StagedChange { path: "notes/todo.md".to_string(), old_path: None, status: Delta::Untracked },
+            StagedChange { path: "tracked.txt".to_string(), old_path: None, status: Delta::Modified },
+        ]);
+    }
+
+    #[test]
+    fn test_staged_stats() {
+        let temp_dir = tempdir().unwrap();
+        let repo = Repository::init(temp_dir.path()).unwrap();
+
+        std::fs::write(temp_dir.path().join("lib.rs"), "one\ntwo\nthree\n").unwrap();
+        add(&[".".to_string()], Some(&repo)).unwrap();
+        let signature = Signature::now("Test User", "test@example.com").unwrap();
+        let tree = repo.find_tree(repo.index().unwrap().write_tree().unwrap()).unwrap();
+        repo.commit(Some("HEAD"), &signature, &signature, "Initial commit", &tree, &[]).unwrap();
+
+        std::fs::write(temp_dir.path().join("lib.rs"), "one\n2\nthree\nfour\n").unwrap();
+        std::fs::write(temp_dir.path().join("logo.png"), [0u8, 159, 146, 150]).unwrap();
+        add(&[".".to_string()], Some(&repo)).unwrap();
+
+        let mut stats = staged_stats(&repo, Some(&tree)).unwrap();
+        stats.sort_by(|a, b| a.change.path.cmp(&b.change.path));
+        let counts: Vec<(&str, usize, usize, bool)> = stats.iter()
+            .map(|s| (s.change.path.as_str(), s.insertions, s.deletions, s.binary))
+            .collect();
+        assert_eq!(counts, vec![("lib.rs", 2, 1, false), ("logo.png", 0, 0, true)]);
+
+        // against no tree, everything is added
+        let stats = staged_stats(&repo, None).unwrap();
+        assert!(stats.iter().all(|s| s.change.status == Delta::Added));
+        assert_eq!(stats.iter().map(|s| s.insertions).sum::<usize>(), 4);
+    }
+}
@@ -0,0 +1,501 @@
+use git2::Error;
+use regex::Regex;
+use crate::types::emoji::EmojiCatalog;
+
+/// Name of the built-in gitmoji template: `✨ subject`.
+pub const GITMOJI: &str = "gitmoji";
+
+/// Name of the built-in Conventional Commits template with an emoji prefix:
+/// `✨ feat(scope)!: subject`.
+pub const CONVENTIONAL: &str = "conventional";
+
+const GITMOJI_SOURCE: &str = "{emoji} {subject}\n\n{body}\n\n{footers}";
+const CONVENTIONAL_SOURCE: &str = "{emoji} [{type}[({scope})][{breaking}]: ]{subject}\n\n{body}\n\n{footers}";
+
+const PLACEHOLDERS: [&str; 7] = ["emoji", "type", "scope", "breaking", "subject", "body", "footers"];
+
+/// Token of the footer describing a breaking change.
+pub const BREAKING_CHANGE: &str = "BREAKING CHANGE";
+
+/// Tokens of the issue trailers, the only footers written `Token #value`
+/// when the value is an issue number (`Closes #12`).
+pub const CLOSES: &str = "Closes";
+pub const REFS: &str = "Refs";
+
+/// Pattern of a Conventional Commits header following the emoji:
+/// `type(scope)!: `, capturing the type, the scope and the `!`.
+pub const HEADER_PATTERN: &str = r"^([A-Za-z]+)(?:\(([^()]*)\))?(!)?: ";
+
+// a `Token: value` or `Token #value` footer line; `BREAKING CHANGE` is the
+// only token with a space
+const FOOTER_PATTERN: &str = r"^(BREAKING CHANGE|[A-Za-z][A-Za-z0-9-]*)(?:: (.*)| (#.*))$";
+
+/// Subject prefixes of the commits `git rebase --autosquash` folds into an
+/// earlier one.
+pub const AUTOSQUASH_PREFIXES: [&str; 3] = ["fixup! ", "squash! ", "amend! "];
+
+/// The values substituted into a [`Template`].
+///
+/// # Fields
+///
+/// * `emoji` - The rendered emoji prefix (glyph, shortcode or both).
+/// * `commit_type` - The Conventional Commits type, e.g. `feat`.
+/// * `scope` - The scope of the change, e.g. `parser`.
+/// * `subject` - The subject (title) of the commit.
+/// * `body` - The free-form body.
+/// * `footers` - Trailer lines as `(token, value)` pairs, rendered as
+///   `token: value`, or `token #value` for [`CLOSES`] and [`REFS`] values
+///   starting with `#`.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct MessageParts {
+    pub emoji: String,
+    pub commit_type: String,
+    pub scope: String,
+    pub subject: String,
+    pub body: String,
+    pub footers: Vec<(String, String)>,
+}
+
+impl MessageParts {
+    /// Returns whether the parts carry a non-empty `BREAKING CHANGE` footer.
+    pub fn is_breaking(&self) -> bool {
+        self.footers.iter().any(|(token, value)| token == BREAKING_CHANGE && !value.trim().is_empty())
+    }
+
+    /// Splits an existing commit message back into its parts.
+    ///
+    /// The emoji is taken as written (glyph, `:shortcode:` or both), a
+    /// Conventional Commits header is recognised when its type is one of the
+    /// catalog types, and the last paragraph is read as footers when every
+    /// line of it is a `Token: value` or `Token #value` footer. `BREAKING-CHANGE` footers are
+    /// normalised to [`BREAKING_CHANGE`].
+    pub fn parse(message: &str, catalog: &EmojiCatalog) -> Self {
+        let mut paragraphs: Vec<String> = message.trim().split("\n\n")
+            .map(|paragraph| paragraph.trim_matches('\n').to_string())
+            .filter(|paragraph| !paragraph.trim().is_empty())
+            .collect();
+        if paragraphs.is_empty() {
+            return Self::default();
+        }
+
+        let first = paragraphs.remove(0);
+        let (header, rest) = first.split_once('\n').unwrap_or((first.as_str(), ""));
+        if !rest.trim().is_empty() {
+            paragraphs.insert(0, rest.to_string());
+        }
+
+        let mut parts = Self::default();
+        let mut subject = header.trim();
+        if let Some((emoji, len)) = catalog.match_prefix(subject) {
+            let mut end = len;
+            // the `both` format: `✨ :sparkles:`
+            let shortcode = format!(" :{}:", emoji.shortcode);
+            if subject[end..].starts_with(&shortcode) {
+                end += shortcode.len();
+            }
+            parts.emoji = subject[..end].to_string();
+            subject = subject[end..].trim_start();
+        }
+
+        let header = Regex::new(HEADER_PATTERN).expect("valid header pattern");
+        if let Some(captures) = header.captures(subject) {
+            let commit_type = &captures[1];
+            if catalog.emojis().iter().any(|e| e.conventional_type.as_deref() == Some(commit_type)) {
+                parts.commit_type = commit_type.to_string();
+                parts.scope = captures.get(2).map(|m| m.as_str().to_string()).unwrap_or_default();
+                subject = &subject[captures[0].len()..];
+            }
+        }
+        parts.subject = subject.trim().to_string();
+
+        let footer = Regex::new(FOOTER_PATTERN).expect("valid footer pattern");
+        let normalise = |line: &str| match line.strip_prefix("BREAKING-CHANGE") {
+            Some(rest) => format!("{}{}", BREAKING_CHANGE, rest),
+            None => line.to_string(),
+        };
+        if paragraphs.last().is_some_and(|last| last.lines().all(|line| footer.is_match(&normalise(line)))) {
+            let last = paragraphs.pop().unwrap_or_default();
+            parts.footers = last.lines()
+                .filter_map(|line| footer.captures(&normalise(line)).map(|c| {
+                    let value = c.get(2).or(c.get(3)).map(|m| m.as_str()).unwrap_or_default();
+                    (c[1].to_string(), value.trim().to_string())
+                }))
+                .collect();
+        }
+        parts.body = paragraphs.join("\n\n");
+        parts
+    }
+
+    fn value(&self, placeholder: &str) -> String {
+        match placeholder {
+            "breaking" if self.is_breaking() => "!".to_string(),
+            "emoji" => self.emoji.trim().to_string(),
+            "type" => self.commit_type.trim().to_string(),
+            "scope" => self.scope.trim().to_string(),
+            "subject" => self.subject.trim().to_string(),
+            "body" => self.body.trim().to_string(),
+            "footers" => self.footers.iter()
+                .filter(|(_, value)| !value.trim().is_empty())
+                .map(|(token, value)| match value.trim() {
+                    value if value.starts_with('#') && [CLOSES, REFS].contains(&token.as_str()) => {
+                        format!("{} {}", token, value)
+                    }
+                    value => format!("{}: {}", token, value),
+                })
+                .collect::<Vec<String>>()
+                .join("\n"),
+            _ => String::new(),
+        }
+    }
+}
+
+// a parsed template element
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Node {
+    Text(String),
+    Placeholder(String),
+    // `[ ... ]`: dropped when one of its placeholders is empty
+    Optional(Vec<Node>),
+}
+
+/// A commit message template.
+///
+/// Templates are plain text with `{emoji}`, `{type}`, `{scope}`, `{breaking}`,
+/// `{subject}`, `{body}` and `{footers}` placeholders. `{breaking}` renders as
+/// `!` when a [`BREAKING_CHANGE`] footer is present.
+///
+/// Text between square brackets is optional: it is dropped when any
+/// placeholder directly inside it is empty, so `[({scope})]` disappears
+/// without a scope. Brackets and braces can be written literally by escaping
+/// them with a backslash.
+///
+/// Paragraphs are separated by blank lines; empty paragraphs are dropped, so
+/// a message without body or footers is just its subject line. A template
+/// without `{footers}` still ends with the footers, in a last paragraph, so
+/// that trailers and breaking changes are never lost.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    nodes: Vec<Node>,
+}
+
+impl Template {
+    /// Parses a template source.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `Err(Error)` if the source references an unknown
+    /// placeholder or has unbalanced braces or brackets.
+    pub fn parse(source: &str) -> Result<Self, Error> {
+        let mut chars = source.chars();
+        let nodes = parse_nodes(&mut chars, false)?;
+        Ok(Self { nodes })
+    }
+
+    /// Resolves a configured template: either the name of a built-in
+    /// template ([`GITMOJI`], [`CONVENTIONAL`]) or a custom template source.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `Err(Error)` if a custom source cannot be parsed.
+    pub fn resolve(value: &str) -> Result<Self, Error> {
+        match value.trim() {
+            GITMOJI => Self::parse(GITMOJI_SOURCE),
+            CONVENTIONAL => Self::parse(CONVENTIONAL_SOURCE),
+            custom => Self::parse(custom),
+        }
+    }
+
+    /// Returns whether the template references the given placeholder.
+    pub fn uses(&self, placeholder: &str) -> bool {
+        fn any(nodes: &[Node], placeholder: &str) -> bool {
+            nodes.iter().any(|node| match node {
+                Node::Placeholder(name) => name == placeholder,
+                Node::Optional(inner) => any(inner, placeholder),
+                Node::Text(_) => false,
+            })
+        }
+        any(&self.nodes, placeholder)
+    }
+
+    /// Renders the commit message for the given parts.
+    ///
+    /// The subject line is trimmed, trailing whitespace is removed from every
+    /// line, empty paragraphs are dropped and the message ends with a newline.
+    pub fn render(&self, parts: &MessageParts) -> String {
+        let mut raw = render_nodes(&self.nodes, parts);
+        if !self.uses("footers") {
+            raw.push_str("\n\n");
+            raw.push_str(&parts.value("footers"));
+        }
+
+        let paragraphs: Vec<String> = raw.split("\n\n")
+            .map(|paragraph| paragraph.lines()
+                .map(str::trim_end)
+                .collect::<Vec<&str>>()
+                .join("\n")
+                .trim_matches('\n')
+                .to_string())
+            .filter(|paragraph| !paragraph.trim().is_empty())
+            .collect();
+
+        match paragraphs.split_first() {
+            Some((subject, rest)) => {
+                let mut message = subject.trim_start().to_string();
+                for paragraph in rest {
+                    message.push_str("\n\n");
+                    message.push_str(paragraph);
+                }
+                message.push('\n');
+                message
+            }
+            None => String::new(),
+        }
+    }
+}
+
+/// Strips the autosquash prefixes of a subject line (`fixup! `, `squash! `,
+/// `amend! `, possibly repeated), leaving the subject of the targeted commit.
+pub fn strip_autosquash(subject: &str) -> &str {
+    let mut subject = subject;
+    while let Some(rest) = AUTOSQUASH_PREFIXES.iter().find_map(|prefix| subject.strip_prefix(prefix)) {
+        subject = rest;
+    }
+    subject
+}
+
+// parses nodes until the end of the input, or the closing `]` of a group
+fn parse_nodes(chars: &mut std::str::Chars, in_group: bool) -> Result<Vec<Node>, Error> {
+    let mut nodes = Vec::new();
+    let mut text = String::new();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some(escaped) => text.push(escaped),
+                None => text.push('\\'),
+            },
+            '{' => {
+                let mut name = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => name.push(c),
+                        None => return Err(Error::from_str("unclosed '{' in template")),
+                    }
+                }
+                if !PLACEHOLDERS.contains(&name.as_str()) {
+                    return Err(Error::from_str(format!("unknown template placeholder '{{{}}}'", name).as_str()));
+                }
+                flush_text(&mut nodes, &mut text);
+                nodes.push(Node::Placeholder(name));
+            }
+            '[' => {
+                flush_text(&mut nodes, &mut text);
+                nodes.push(Node::Optional(parse_nodes(chars, true)?));
+            }
+            ']' if in_group => {
+                flush_text(&mut nodes, &mut text);
+                return Ok(nodes);
+            }
+            '}' | ']' => return Err(Error::from_str(format!("unbalanced '{}' in template", c).as_str())),
+            _ => text.push(c),
+        }
+    }
+
+    if in_group {
+        return Err(Error::from_str("unclosed '[' in template"));
+    }
+    flush_text(&mut nodes, &mut text);
+    Ok(nodes)
+}
+
+fn flush_text(nodes: &mut Vec<Node>, text: &mut String) {
+    if !text.is_empty() {
+        nodes.push(Node::Text(std::mem::take(text)));
+    }
+}
+
+// renders a sequence of nodes, dropping the optional groups that lack a value
+fn render_nodes(nodes: &[Node], parts: &MessageParts) -> String {
+    let mut output = String::new();
+    for node in nodes {
+        match node {
+            Node::Text(text) => output.push_str(text),
+            Node::Placeholder(name) => output.push_str(&parts.value(name)),
+            Node::Optional(inner) => {
+                if let Some(rendered) = render_group(inner, parts) {
+                    output.push_str(&rendered);
+                }
+            }
+        }
+    }
+    output
+}
+
+// a group is kept when all its direct placeholders have a value and, if it
+// has none, when at least one of its nested groups is kept
+fn render_group(nodes: &[Node], parts: &MessageParts) -> Option<String> {
+    let direct: Vec<&String> = nodes.iter()
+        .filter_map(|node| match node {
+            Node::Placeholder(name) => Some(name),
+            _ => None,
+        })
+        .collect();
+
+    if direct.iter().any(|name| parts.value(name).is_empty()) {
+        return None;
+    }
+    if direct.is_empty() {
+        let any_kept = nodes.iter().any(|node| match node {
+            Node::Optional(inner) => render_group(inner, parts).is_some(),
+            _ => false,
+        });
+        if !any_kept {
+            return None;
+        }
+    }
+    Some(render_nodes(nodes, parts))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parts() -> MessageParts {
+        MessageParts {
+            emoji: "✨".to_string(),
+            commit_type: "feat".to_string(),
+            scope: "parser".to_string(),
+            subject: "support tables".to_string(),
+            body: "Tables are now parsed.\n\nNested ones too.".to_string(),
+            footers: vec![(BREAKING_CHANGE.to_string(), "the AST changed".to_string())],
+        }
+    }
+
+    #[test]
+    fn test_gitmoji_template() {
+        let template = Template::resolve(GITMOJI).unwrap();
+
+        assert_eq!(
+            template.render(&parts()),
+            "✨ support tables\n\nTables are now parsed.\n\nNested ones too.\n\nBREAKING CHANGE: the AST changed\n"
+        );
+    }
+
+    #[test]
+    fn test_empty_sections_are_dropped() {
+        let template = Template::resolve(GITMOJI).unwrap();
+        let parts = MessageParts {
+            body: String::new(),
+            footers: vec![(BREAKING_CHANGE.to_string(), " ".to_string())],
+            ..parts()
+        };
+
+        assert_eq!(template.render(&parts), "✨ support tables\n");
+    }
+
+    #[test]
+    fn test_conventional_template() {
+        let template = Template::resolve(CONVENTIONAL).unwrap();
+        let mut parts = MessageParts { body: String::new(), footers: vec![], ..parts() };
+
+        assert_eq!(template.render(&parts), "✨ feat(parser): support tables\n");
+
+        parts.scope = String::new();
+        assert_eq!(template.render(&parts), "✨ feat: support tables\n");
+
+        parts.footers = vec![(BREAKING_CHANGE.to_string(), "the AST changed".to_string())];
+        assert_eq!(template.render(&parts), "✨ feat!: support tables\n\nBREAKING CHANGE: the AST changed\n");
+        parts.footers = vec![(BREAKING_CHANGE.to_string(), String::new())];
+
+        parts.commit_type = String::new();
+        assert_eq!(template.render(&parts), "✨ support tables\n");
+
+        parts.emoji = String::new();
+        assert_eq!(template.render(&parts), "support tables\n");
+    }
+
+    #[test]
+    fn test_custom_template() {
+        let template = Template::resolve("[\\[{scope}\\] ]{subject} {emoji}\n\n{body}").unwrap();
+
+        assert!(template.uses("scope"));
+        assert!(!template.uses("footers"));
+        assert_eq!(
+            template.render(&MessageParts { body: String::new(), footers: Vec::new(), ..parts() }),
+            "[parser] support tables ✨\n"
+        );
+
+        // the footers are appended when the template has no place for them
+        assert_eq!(
+            template.render(&MessageParts { body: String::new(), ..parts() }),
+            "[parser] support tables ✨\n\nBREAKING CHANGE: the AST changed\n"
+        );
+        let template = Template::resolve("{emoji} {subject}").unwrap();
+        assert_eq!(template.render(&parts()), "✨ support tables\n\nBREAKING CHANGE: the AST changed\n");
+    }
+
+    #[test]
+    fn test_parse_message() {
+        let catalog = EmojiCatalog::builtin();
+
+        let message = "✨ feat(parser)!: support tables\n\nTables are now parsed.\n\nNested ones too.\n\n\
+            BREAKING CHANGE: the AST changed\nCloses #12\nRefs: PROJ-7\n";
+        let parsed = MessageParts::parse(message, &catalog);
+        assert_eq!(parsed, MessageParts {
+            footers: vec![
+                (BREAKING_CHANGE.to_string(), "the AST changed".to_string()),
+                ("Closes".to_string(), "#12".to_string()),
+                ("Refs".to_string(), "PROJ-7".to_string()),
+            ],
+            ..parts()
+        });
+        assert_eq!(Template::resolve(CONVENTIONAL).unwrap().render(&parsed), message);
+
+        // only issue trailers take the `#` form
+        let parsed = MessageParts::parse("🐛 fix crash\n\nFixes #3\nRefs #4\n", &catalog);
+        assert_eq!(Template::resolve(GITMOJI).unwrap().render(&parsed), "🐛 fix crash\n\nFixes: #3\nRefs #4\n");
+
+        let parsed = MessageParts::parse("🐛 :bug: note: crash fixed\n\nBREAKING-CHANGE: none\n", &catalog);
+        assert_eq!(parsed.emoji, "🐛 :bug:");
+        assert_eq!(parsed.commit_type, "");
+        assert_eq!(parsed.subject, "note: crash fixed");
+        assert_eq!(parsed.body, "");
+        assert!(parsed.is_breaking());
+
+        let parsed = MessageParts::parse("plain subject\n\nJust a body: with a colon and spaces.\n", &catalog);
+        assert_eq!(parsed.emoji, "");
+        assert_eq!(parsed.subject, "plain subject");
+        assert_eq!(parsed.body, "Just a body: with a colon and spaces.");
+        assert!(parsed.footers.is_empty());
+    }
+
+    #[test]
+    fn test_breaking_change_starting_with_hash() {
+        let parts = MessageParts {
+            footers: vec![(BREAKING_CHANGE.to_string(), "#12 the --x flag is gone".to_string())],
+            ..parts()
+        };
+        let message = Template::resolve(GITMOJI).unwrap().render(&parts);
+        assert!(message.ends_with("\n\nBREAKING CHANGE: #12 the --x flag is gone\n"));
+
+        let linter = crate::functions::lint::Linter::new(crate::types::config::Config::default()).unwrap();
+        assert!(linter.lint_committed(&message).is_empty());
+        assert!(MessageParts::parse(&message, &EmojiCatalog::builtin()).is_breaking());
+    }
+
+    #[test]
+    fn test_strip_autosquash() {
+        assert_eq!(strip_autosquash("fixup! 🐛 fix crash"), "🐛 fix crash");
+        assert_eq!(strip_autosquash("squash! fixup! ✨ add login"), "✨ add login");
+        assert_eq!(strip_autosquash("🐛 fixup! not a prefix"), "🐛 fixup! not a prefix");
+    }
+
+    #[test]
+    fn test_invalid_templates() {
+        assert!(Template::parse("{emoji} {title}").is_err());
+        assert!(Template::parse("{emoji").is_err());
+        assert!(Template::parse("[{scope}").is_err());
+        assert!(Template::parse("{subject}]").is_err());
+    }
+}